@@ -55,7 +55,11 @@ pub fn chal4() {
 	let fname = "src/set1/4.txt";
 
 	for line in read_to_string(fname).unwrap().lines() {
-		let xored = CryptoData::from_hex(line);
+		// skip corrupted lines
+		let xored = match CryptoData::try_from_hex(line) {
+			Ok(xored) => xored,
+			Err(e) => { println!("skipping line: {}", e); continue }
+		};
 		let (_, line_best, line_best_score) = guess_xor_byte(&xored);
 
 		if line_best_score > best_score {
@@ -65,7 +69,7 @@ pub fn chal4() {
 	}
 
 	println!("best score: {}", best_score);
-	println!("text: {}", best.to_text());
+	println!("text: {}", String::from_utf8_lossy(best.vec()));
 	println!("hex: {}", best.to_hex());
 }
 
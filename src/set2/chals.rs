@@ -56,11 +56,9 @@ fn encryption_oracle(input: CryptoData) -> CryptoData {
 
 fn encrypted_with_ECB(ciphertext: CryptoData, bsize: usize) -> bool {
 	let mut block_set = HashSet::new();
-	let ciph_vec = ciphertext.vec();
 	let mut dups = 0;
 
-	for idx in (0..ciph_vec.len()).step_by(bsize) {
-		let block = &ciph_vec[idx..idx + bsize];
+	for block in ciphertext.chunks(bsize) {
 		if block_set.contains(&block) {
 			//println!("dup block ({}): {}", idx, block);
 			dups += 1;
@@ -101,10 +99,9 @@ fn create_table(input: &CryptoData, key: &CryptoData, blocksize: usize) -> HashM
 		let cated = input.cat(&byte);
 		let output = oracle_12(&cated, key);
 
-		let block_slice = &output.vec()[0..blocksize];
-		let first_block = CryptoData::from_vec(&block_slice.to_vec());
+		let first_block = output.slice(0, blocksize);
 
-		byte_block.insert(first_block.clone(), b);
+		byte_block.insert(first_block, b);
 	}
 	byte_block
 }
@@ -126,20 +123,18 @@ YnkK";
 
 	let table = create_table(&short_block, &key, blocksize);
 	let mut res = Vec::new();
-	let mut secvec = secret.vec().clone();
 
-	while secvec.len() > 0 {
-		let cat_block = short_block.cat(&CryptoData::from_vec(&secvec));
+	for pos in 0..secret.len() {
+		let mut cat_block = short_block.clone();
+		cat_block.extend(secret.view(pos, secret.len()));
 		let short_enc = oracle_12(&cat_block, &key);
 
-		let block_slice = &short_enc.vec()[0..blocksize];
-		let first_block = CryptoData::from_vec(&block_slice.to_vec());
+		let first_block = short_enc.slice(0, blocksize);
 
 		let byte = table.get(&first_block).unwrap();
 		res.push(*byte);
-		secvec.remove(0);
 	}
-	println!("Deciphered:\n{}", CryptoData::from_vec(&res).to_text());
+	println!("Deciphered:\n{}", CryptoData::from_owned(res).to_text());
 }
 
 fn parse_object(text: &str) -> HashMap<String, String> {
@@ -191,8 +186,15 @@ pub fn chal13() {
 	//println!("tampered: {}", tampered);
 
 	let dec_profile = tampered.ECB_decrypt(&key);
-	println!("text {}", dec_profile.to_text());
-	let parsed_profile = parse_object(&dec_profile.to_text());
+	let text = match dec_profile.try_to_text() {
+		Ok(text) => text,
+		Err(_) => {
+			println!("invalid profile: {}", dec_profile);
+			return;
+		}
+	};
+	println!("text {}", text);
+	let parsed_profile = parse_object(&text);
 
 	match parsed_profile.get("role") {
 		Some(role) => match role.as_str() {
@@ -226,17 +228,16 @@ YnkK";
 
 	let table = create_table(&short_block, &key, blocksize);
 	let mut res = Vec::new();
-	let mut secvec = secret.vec().clone();
 
-	while secvec.len() > 0 {
-		let cat_block = short_block.cat(&CryptoData::from_vec(&secvec));
+	for pos in 0..secret.len() {
+		let mut cat_block = short_block.clone();
+		cat_block.extend(secret.view(pos, secret.len()));
 		let short_enc = oracle_12(&cat_block, &key);
 		let first_block = short_enc.slice(0, blocksize);
 		let byte = table.get(&first_block).unwrap();
 		res.push(*byte);
-		secvec.remove(0);
 	}
-	println!("Deciphered:\n{}", CryptoData::from_vec(&res).to_text());
+	println!("Deciphered:\n{}", CryptoData::from_owned(res).to_text());
 }
 
 // PKCS#7 padding validation
@@ -43,7 +43,7 @@ fn guess_block(c1: &CryptoData, c2: &CryptoData, key: &CryptoData, iv: &CryptoDa
 
 		// prepare end bytes for c_my vector
 		for j in 0..i {
-			let xored = (i as u8 + 1) ^ p2_bytes[15 - j] ^ c1.vec()[15 - j];
+			let xored = (i as u8 + 1) ^ p2_bytes[15 - j] ^ c1[15 - j];
 			end_bytes_vec[15 - j] = xored;
 		}
 
@@ -60,7 +60,7 @@ fn guess_block(c1: &CryptoData, c2: &CryptoData, key: &CryptoData, iv: &CryptoDa
 		}
 
 		// recover a byte of the second block of the plaintext
-		let plain_byte = (i as u8 + 1) ^ c1.vec()[15 - i] ^ p2_mod;
+		let plain_byte = (i as u8 + 1) ^ c1[15 - i] ^ p2_mod;
 		p2_bytes[15 - i] = plain_byte;
 	}
 	CryptoData::from_vec(&p2_bytes)
@@ -112,8 +112,9 @@ pub fn chal19() {
 // Break fixed-nonce CTR statistically
 pub fn chal20() {
 	let fname = "src/set3/20.txt";
+	// skip lines that fail to decode
 	let lines: Vec<CryptoData> = read_to_string(fname).unwrap().lines()
-	    							.map(|x| CryptoData::from_base64(x)).collect();
+	    							.filter_map(|x| CryptoData::try_from_base64(x).ok()).collect();
 
 	// use the length of the shortest line as keysize
 	let keysize = lines.iter().min_by_key(|x| x.len()).unwrap().len();
@@ -122,7 +123,10 @@ pub fn chal20() {
 	let key = guess_xor_key(&truncated.iter().fold(CryptoData::new(), |a, b| a.cat(b)), keysize);
 	let dec_texts: Vec<CryptoData> = truncated.iter().map(|x| x.xor(&key)).collect();
 	for dec in dec_texts.iter() {
-		println!("{}", dec.to_text());
+		match dec.try_to_text() {
+			Ok(text) => println!("{}", text),
+			Err(_) => println!("{}", dec),
+		}
 	}
 }
 
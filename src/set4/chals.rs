@@ -7,58 +7,36 @@
 //use time::{precise_time_ns};
 use std::fs::read_to_string;
 use std::time::Instant;
+use rand::Rng;
 use ureq::Error;
 
+use utils::aes::{Aes};
 use utils::cryptodata::{CryptoData};
+use utils::ctr::{CounterBlock, CounterLayout, CtrKeystream};
+use utils::lenext::{find_forgery};
+use utils::md4::{Md4};
+use utils::sha1::{Sha1};
 use utils::utils::{decrypt_and_find_CTR,flip_bits,wrap_and_encrypt_CBC,wrap_and_encrypt_CTR};
 
-// TODO: skip blocks before offset
 fn edit_ctr(ciphertext: &CryptoData, key: &CryptoData, nonce: &CryptoData, counter: u64, offset: usize, newtext: &CryptoData) -> CryptoData {
 	assert!(offset < ciphertext.len());
-	let plain = ciphertext.CTR_decrypt(key, nonce, counter);
-/*
-	let block = offset / 16;
-	let ncounter = counter + block as u64;
-	let cut_cipher = ciphertext.slice(block * 16, ciphertext.len());
-	let plain = cut_cipher.CTR_decrypt(key, nonce, ncounter);
-*/
-	let notail = plain.cut(offset).cat(newtext);
-	let modified = if plain.len() > offset + newtext.len() {
-			let rest = plain.slice(offset + newtext.len(), plain.len());
-			notail.cat(&rest)
-		} else {
-			notail
-		};
-	modified.CTR_encrypt(key, nonce, counter)
-/*
-	let skipped = ciphertext.cut(block * 16);
-	skipped.cat(&modified.CTR_encrypt(key, nonce, counter))
-*/
+	let start = CounterBlock::from_nonce(nonce, counter, CounterLayout::LittleEndian64, 16).unwrap();
+	let keystream = CtrKeystream::new(Aes::new(key).unwrap(), start);
+
+	let mut modified = ciphertext.clone();
+	modified.CTR_edit(&keystream, offset, newtext);
+	modified
 }
 
 fn crack_edit_ctr(ciphertext: &CryptoData, key: &CryptoData, nonce: &CryptoData, counter: u64) -> CryptoData {
-	let mut result = CryptoData::new();
-	for idx in 0..ciphertext.len() {
-		println!("idx: {}", idx);
-		for b in 0u8..=255 {
-			println!("b: {}", b);
-			let byte = CryptoData::from_byte(b);
-			let mod_enc = edit_ctr(ciphertext, key, nonce, counter, idx, &byte);
-			if mod_enc == *ciphertext {
-				result = result.cat(&byte);
-				println!("guessed so far: {}", result.to_text());
-				break;
-			}
-		}
-	}
-	result
+	// overwriting the plaintext with zeros gives us the keystream
+	let zeros = CryptoData::zero(ciphertext.len());
+	let keystream = edit_ctr(ciphertext, key, nonce, counter, 0, &zeros);
+	ciphertext.xor(&keystream)
 }
 
 // Break "random access read/write" AES CTR
 pub fn chal25() {
-	//TODO: this challenge runs *really* slowly!
-	//the CryptoData implementation should be checked
-
 	let key_ctr = CryptoData::random(16);
 	let nonce = CryptoData::random(8);
 	let counter = 100u64;
@@ -69,8 +47,7 @@ pub fn chal25() {
 
 	let key_ecb = CryptoData::from_text("YELLOW SUBMARINE");
 	let encrypted = CryptoData::from_base64(&base64_str);
-	//let text = encrypted.ECB_decrypt(&key_ecb);
-	let text = CryptoData::from_text("abcdefghijklmnopqrstuvwxyzQWFPGJLUY:ARSTDHNENEIIZXCVBKM1234567890[]['o'o,.,/,`");
+	let text = encrypted.ECB_decrypt(&key_ecb);
 	let enc = text.CTR_encrypt(&key_ctr, &nonce, counter);
 	let cracked = crack_edit_ctr(&enc, &key_ctr, &nonce, counter);
 
@@ -127,8 +104,13 @@ pub fn chal27() {
 	let first = enc.block(0, 16);
 	let tampered = first.cat(&zeros).cat(&first).cat(&enc.slice(3 * 16, enc.len()));
 	let dec = tampered.CBC_decrypt(&key, &key);
-	if !check_valid_ascii(&dec.to_text()) {
+	// invalid UTF-8 isn't valid ASCII either
+	let valid = match dec.try_to_text() {
+		Ok(text) => check_valid_ascii(&text),
+		Err(_) => false,
+	};
 	//if !check_valid_ascii(&dec) {
+	if !valid {
 		println!("invalid input: {}", dec);
 	}
 	let plain = dec.block(0, 16).xor(&dec.block(2, 16));
@@ -146,18 +128,45 @@ pub fn chal28() {
 
 // Break a SHA-1 keyed MAC using length extension
 pub fn chal29() {
-	//TODO
-/*
-	let msg = "comment1=cooking%20MCs;userdata=foo;comment2=%20like%20a%20pound%20of%20bacon";
-	let prefix = CryptoData::from_text(msg);
-	let padding = prefix.len();
-	let postfix = ";admin=true";
-*/
+	let mut rng = rand::rng();
+	let key = CryptoData::random(rng.random_range(4..32));
+
+	let msg = CryptoData::from_text("comment1=cooking%20MCs;userdata=foo;comment2=%20like%20a%20pound%20of%20bacon");
+	let mac = msg.SHA1_mac_prefix(&key);
+	let suffix = CryptoData::from_text(";admin=true");
+
+	// the key length is unknown, the server tells us if the MAC is right
+	let oracle = |m: &[u8], t: &[u8]| CryptoData::from(m).SHA1_mac_prefix(&key).ct_eq(&CryptoData::from(t));
+	match find_forgery::<Sha1, _>(&oracle, &mac, &msg, &suffix, 0..64) {
+		Ok(Some(forgery)) => {
+			println!("key length: {}", forgery.key_len);
+			println!("forged message: {}", String::from_utf8_lossy(&forgery.message));
+			println!("forged MAC: {}", forgery.mac);
+		},
+		Ok(None) => println!("length extension failed"),
+		Err(e) => println!("length extension failed: {}", e),
+	}
 }
 
 // Break an MD4 keyed MAC using length extension
 pub fn chal30() {
-	//TODO
+	let mut rng = rand::rng();
+	let key = CryptoData::random(rng.random_range(4..32));
+
+	let msg = CryptoData::from_text("comment1=cooking%20MCs;userdata=foo;comment2=%20like%20a%20pound%20of%20bacon");
+	let mac = msg.MD4_mac_prefix(&key);
+	let suffix = CryptoData::from_text(";admin=true");
+
+	let oracle = |m: &[u8], t: &[u8]| CryptoData::from(m).MD4_mac_prefix(&key).ct_eq(&CryptoData::from(t));
+	match find_forgery::<Md4, _>(&oracle, &mac, &msg, &suffix, 0..64) {
+		Ok(Some(forgery)) => {
+			println!("key length: {}", forgery.key_len);
+			println!("forged message: {}", String::from_utf8_lossy(&forgery.message));
+			println!("forged MAC: {}", forgery.mac);
+		},
+		Ok(None) => println!("length extension failed"),
+		Err(e) => println!("length extension failed: {}", e),
+	}
 }
 
 //TODO: terminate on code 200
@@ -1,31 +1,594 @@
+use std::fmt;
+use std::fs::read_to_string;
+use std::io;
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+
+use utils::bignum::{BigInt};
+use utils::cryptodata::{CryptoData,CryptoError};
+use utils::dh::{derive_key,DhGroup};
+use utils::protocol::{link,mitm_link,Endpoint,ProtocolError,Side,TcpTransport,Transport};
+use utils::sha1::{Sha1};
+use utils::sha2::{Sha256};
+use utils::srp::{client_login,private_key,proof,server_login,session_key,simple_client_login,simple_server_login,SimpleSrpServer,SrpClient,SrpMessage,SrpParams,SrpServer,VerifierStore};
+
 // Implement Diffie-Hellman
 pub fn chal33() {
-	//TODO
+	// toy numbers first
+	let toy = DhGroup::toy();
+	let a = toy.generate_keypair();
+	let b = toy.generate_keypair();
+	let s_a = a.public().modexp(b.private(), &toy.p);
+	let s_b = b.public().modexp(a.private(), &toy.p);
+	println!("p = {}, g = {}: A = {}, B = {}, s = {} / {}", toy.p.to_dec(), toy.g.to_dec(),
+		a.public().to_dec(), b.public().to_dec(), s_a.to_dec(), s_b.to_dec());
+	assert_eq!(s_a, s_b);
+
+	let group = DhGroup::nist();
+	let alice = group.generate_keypair();
+	let bob = group.generate_keypair();
+	let key_a = alice.shared_key::<Sha256>(bob.public(), 16).unwrap();
+	let key_b = bob.shared_key::<Sha256>(alice.public(), 16).unwrap();
+	println!("A = {}", alice.public());
+	println!("B = {}", bob.public());
+	println!("key: {} / {}", key_a, key_b);
+	assert_eq!(key_a, key_b);
+
+	// and a degenerate public key is refused
+	println!("B = p: {}", alice.shared_secret(&group.p).unwrap_err());
+	println!("B = 1: {}", alice.shared_secret(&BigInt::one()).unwrap_err());
+}
+
+// Messages of the chal34 echo protocol
+#[derive (Clone, Debug)]
+pub enum EchoMessage {
+	// p, g, A
+	Params(BigInt, BigInt, BigInt),
+	// B
+	PublicKey(BigInt),
+	// AES-CBC(SHA1(s)[0:16], iv, msg) + iv
+	Data(CryptoData),
+}
+
+// the message one party ended up with
+pub type EchoResult = Result<CryptoData, ProtocolError>;
+
+fn echo_key(secret: &BigInt) -> Result<CryptoData, ProtocolError> {
+	Ok(derive_key::<Sha1>(secret, 16)?)
+}
+
+fn echo_encrypt(key: &CryptoData, msg: &CryptoData) -> CryptoData {
+	let iv = CryptoData::random(16);
+	msg.CBC_encrypt(key, &iv).cat(&iv)
+}
+
+fn echo_decrypt(key: &CryptoData, data: &CryptoData) -> Result<CryptoData, ProtocolError> {
+	if data.len() < 32 {
+		return Err(ProtocolError::Unexpected("ciphertext + iv"));
+	}
+	let (ct, iv) = (data.slice(0, data.len() - 16), data.slice(data.len() - 16, data.len()));
+	Ok(ct.try_CBC_decrypt(key, &iv)?.try_pad_strip(16)?)
+}
+
+// Alice sends the group, her public key and msg, returns what comes back
+pub fn echo_alice(ep: Endpoint<EchoMessage>, group: &DhGroup, msg: &CryptoData, validate: bool) -> Result<CryptoData, ProtocolError> {
+	let mut alice = group.generate_keypair();
+	alice.set_validation(validate);
+	ep.send(EchoMessage::Params(group.p.clone(), group.g.clone(), alice.public().clone()))?;
+
+	let b = match ep.recv()? {
+		EchoMessage::PublicKey(b) => b,
+		_ => return Err(ProtocolError::Unexpected("public key")),
+	};
+	let key = echo_key(&alice.shared_secret(&b)?)?;
+
+	ep.send(EchoMessage::Data(echo_encrypt(&key, msg)))?;
+	match ep.recv()? {
+		EchoMessage::Data(data) => echo_decrypt(&key, &data),
+		_ => Err(ProtocolError::Unexpected("echo")),
+	}
+}
+
+// Bob accepts whatever group Alice proposes and echoes her message,
+// returns the message
+pub fn echo_bob(ep: Endpoint<EchoMessage>, validate: bool) -> Result<CryptoData, ProtocolError> {
+	let (p, g, a) = match ep.recv()? {
+		EchoMessage::Params(p, g, a) => (p, g, a),
+		_ => return Err(ProtocolError::Unexpected("group parameters")),
+	};
+	let mut bob = DhGroup::new(p, g).generate_keypair();
+	bob.set_validation(validate);
+	ep.send(EchoMessage::PublicKey(bob.public().clone()))?;
+	let key = echo_key(&bob.shared_secret(&a)?)?;
+
+	let msg = match ep.recv()? {
+		EchoMessage::Data(data) => echo_decrypt(&key, &data)?,
+		_ => return Err(ProtocolError::Unexpected("message")),
+	};
+	ep.send(EchoMessage::Data(echo_encrypt(&key, &msg)))?;
+	Ok(msg)
+}
+
+// Replace both public keys with p, so both sides end up with s = 0
+// and Mallory can read everything. Returns the messages seen.
+pub fn echo_key_fixing(validate: bool, msg: &CryptoData) -> (EchoResult, EchoResult, Vec<CryptoData>) {
+	let (alice_ep, bob_ep, mut mallory) = mitm_link();
+
+	let bob = thread::spawn(move || echo_bob(bob_ep, validate));
+	let mallory = thread::spawn(move || {
+		let mut seen = Vec::new();
+		let mut fixed = BigInt::zero();
+		mallory.relay(|from, msg| match (from, msg) {
+			(Side::Alice, EchoMessage::Params(p, g, _)) => {
+				fixed = p.clone();
+				Some(EchoMessage::Params(p, g, fixed.clone()))
+			},
+			(Side::Bob, EchoMessage::PublicKey(_)) => Some(EchoMessage::PublicKey(fixed.clone())),
+			(_, EchoMessage::Data(data)) => {
+				if let Ok(plain) = echo_key(&BigInt::zero()).and_then(|key| echo_decrypt(&key, &data)) {
+					seen.push(plain);
+				}
+				Some(EchoMessage::Data(data))
+			},
+			(_, msg) => Some(msg),
+		});
+		seen
+	});
+
+	let alice = echo_alice(alice_ep, &DhGroup::nist(), msg, validate);
+	(alice, bob.join().unwrap(), mallory.join().unwrap())
 }
 
 // Implement a MITM key-fixing attack on Diffie-Hellman with parameter injection
 pub fn chal34() {
-	//TODO
+	let msg = CryptoData::from_text("Cooking MC's like a pound of bacon");
+
+	// the protocol itself
+	let (alice_ep, bob_ep) = link();
+	let bob = thread::spawn(move || echo_bob(bob_ep, true));
+	let echo = echo_alice(alice_ep, &DhGroup::nist(), &msg, true).unwrap();
+	assert_eq!(bob.join().unwrap().unwrap(), msg);
+	assert_eq!(echo, msg);
+	println!("echo: {}", echo.to_text());
+
+	// Alice and Bob don't notice anything
+	let (alice, bob, seen) = echo_key_fixing(false, &msg);
+	assert_eq!(alice.unwrap(), msg);
+	assert_eq!(bob.unwrap(), msg);
+	assert_eq!(seen, vec![msg.clone(), msg.clone()]);
+	for m in &seen {
+		println!("Mallory read: {}", m.to_text());
+	}
+
+	// checking the public keys stops the attack
+	let (alice, bob, seen) = echo_key_fixing(true, &msg);
+	println!("with validation: Bob {}, Alice {}", bob.unwrap_err(), alice.unwrap_err());
+	assert!(seen.is_empty());
+}
+
+// Messages of the chal35 protocol, the group is agreed on first
+#[derive (Clone, Debug)]
+pub enum NegotiatedMessage {
+	// p, g proposed by Alice
+	Group(BigInt, BigInt),
+	// p, g accepted by Bob
+	Ack(BigInt, BigInt),
+	PublicKey(BigInt),
+	// AES-CBC(SHA1(s)[0:16], iv, msg) + iv
+	Data(CryptoData),
+}
+
+fn recv_public(ep: &Endpoint<NegotiatedMessage>) -> Result<BigInt, ProtocolError> {
+	match ep.recv()? {
+		NegotiatedMessage::PublicKey(key) => Ok(key),
+		_ => Err(ProtocolError::Unexpected("public key")),
+	}
+}
+
+// Alice uses the group Bob acknowledged, returns the echo of msg
+pub fn negotiated_alice(ep: Endpoint<NegotiatedMessage>, group: &DhGroup, msg: &CryptoData, validate: bool) -> Result<CryptoData, ProtocolError> {
+	ep.send(NegotiatedMessage::Group(group.p.clone(), group.g.clone()))?;
+	let group = match ep.recv()? {
+		NegotiatedMessage::Ack(p, g) => DhGroup::new(p, g),
+		_ => return Err(ProtocolError::Unexpected("ACK")),
+	};
+
+	let mut alice = group.generate_keypair();
+	alice.set_validation(validate);
+	ep.send(NegotiatedMessage::PublicKey(alice.public().clone()))?;
+	let key = echo_key(&alice.shared_secret(&recv_public(&ep)?)?)?;
+
+	ep.send(NegotiatedMessage::Data(echo_encrypt(&key, msg)))?;
+	match ep.recv()? {
+		NegotiatedMessage::Data(data) => echo_decrypt(&key, &data),
+		_ => Err(ProtocolError::Unexpected("echo")),
+	}
+}
+
+// Bob accepts the proposed group and echoes the message, returns the message
+pub fn negotiated_bob(ep: Endpoint<NegotiatedMessage>, validate: bool) -> Result<CryptoData, ProtocolError> {
+	let group = match ep.recv()? {
+		NegotiatedMessage::Group(p, g) => DhGroup::new(p, g),
+		_ => return Err(ProtocolError::Unexpected("group")),
+	};
+	ep.send(NegotiatedMessage::Ack(group.p.clone(), group.g.clone()))?;
+
+	let mut bob = group.generate_keypair();
+	bob.set_validation(validate);
+	let a = recv_public(&ep)?;
+	ep.send(NegotiatedMessage::PublicKey(bob.public().clone()))?;
+	let key = echo_key(&bob.shared_secret(&a)?)?;
+
+	let msg = match ep.recv()? {
+		NegotiatedMessage::Data(data) => echo_decrypt(&key, &data)?,
+		_ => return Err(ProtocolError::Unexpected("message")),
+	};
+	ep.send(NegotiatedMessage::Data(echo_encrypt(&key, &msg)))?;
+	Ok(msg)
+}
+
+// Generators Mallory can inject during the negotiation
+#[derive (Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MaliciousG {
+	One,
+	P,
+	PMinusOne,
+}
+
+impl MaliciousG {
+	pub fn value(self, p: &BigInt) -> BigInt {
+		match self {
+			MaliciousG::One => BigInt::one(),
+			MaliciousG::P => p.clone(),
+			MaliciousG::PMinusOne => p - &BigInt::one(),
+		}
+	}
+
+	// the shared secret both sides end up with, from their public keys
+	pub fn predict_secret(self, p: &BigInt, a: &BigInt, b: &BigInt) -> BigInt {
+		match self {
+			// 1^ab
+			MaliciousG::One => BigInt::one(),
+			// p^ab = 0
+			MaliciousG::P => BigInt::zero(),
+			// (-1)^ab is -1 only if both a and b are odd,
+			// i.e. both public keys are -1
+			MaliciousG::PMinusOne => {
+				let p_1 = p - &BigInt::one();
+				if *a == p_1 && *b == p_1 { p_1 } else { BigInt::one() }
+			},
+		}
+	}
+}
+
+impl fmt::Display for MaliciousG {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			MaliciousG::One => write!(f, "g = 1"),
+			MaliciousG::P => write!(f, "g = p"),
+			MaliciousG::PMinusOne => write!(f, "g = p - 1"),
+		}
+	}
+}
+
+// Mallory swaps g in both the proposal and the ACK, then decrypts
+// everything with the predicted secret. Returns what Mallory got out of
+// every message, an error if the prediction was wrong.
+pub fn negotiated_g_injection(injection: MaliciousG, validate: bool, msg: &CryptoData) -> (EchoResult, EchoResult, Vec<EchoResult>) {
+	let (alice_ep, bob_ep, mut mallory) = mitm_link();
+
+	let bob = thread::spawn(move || negotiated_bob(bob_ep, validate));
+	let mallory = thread::spawn(move || {
+		let mut p = BigInt::zero();
+		let mut keys = (BigInt::zero(), BigInt::zero());
+		let mut seen = Vec::new();
+		mallory.relay(|from, msg| match msg {
+			NegotiatedMessage::Group(group_p, _) => {
+				p = group_p;
+				Some(NegotiatedMessage::Group(p.clone(), injection.value(&p)))
+			},
+			NegotiatedMessage::Ack(_, _) => Some(NegotiatedMessage::Ack(p.clone(), injection.value(&p))),
+			NegotiatedMessage::PublicKey(key) => {
+				match from {
+					Side::Alice => keys.0 = key.clone(),
+					Side::Bob => keys.1 = key.clone(),
+				}
+				Some(NegotiatedMessage::PublicKey(key))
+			},
+			NegotiatedMessage::Data(data) => {
+				let secret = injection.predict_secret(&p, &keys.0, &keys.1);
+				seen.push(echo_key(&secret).and_then(|key| echo_decrypt(&key, &data)));
+				Some(NegotiatedMessage::Data(data))
+			},
+		});
+		seen
+	});
+
+	let alice = negotiated_alice(alice_ep, &DhGroup::nist(), msg, validate);
+	(alice, bob.join().unwrap(), mallory.join().unwrap())
 }
 
 // Implement DH with negotiated groups, and break with malicious "g" parameters
 pub fn chal35() {
-	//TODO
+	let msg = CryptoData::from_text("Cooking MC's like a pound of bacon");
+
+	for injection in [MaliciousG::One, MaliciousG::P, MaliciousG::PMinusOne].iter() {
+		let (alice, bob, seen) = negotiated_g_injection(*injection, false, &msg);
+		assert_eq!(alice.unwrap(), msg);
+		assert_eq!(bob.unwrap(), msg);
+		for plain in seen {
+			match plain {
+				Ok(plain) => println!("{}: recovered \"{}\"", injection, plain.to_text()),
+				Err(e) => println!("{}: decryption failed: {}", injection, e),
+			}
+		}
+
+		let (alice, bob, _) = negotiated_g_injection(*injection, true, &msg);
+		println!("{} with validation: Bob {}, Alice {}", injection,
+			bob.map(|m| m.to_text()).unwrap_or_else(|e| e.to_string()),
+			alice.map(|m| m.to_text()).unwrap_or_else(|e| e.to_string()));
+	}
+}
+
+fn srp_server(identity: &str, password: &[u8]) -> SrpServer {
+	let params = SrpParams::nist();
+	let mut store = VerifierStore::new();
+	store.register(&params, identity, password);
+	SrpServer::new(params, store)
 }
 
 // Implement Secure Remote Password (SRP)
 pub fn chal36() {
-	//TODO
+	let email = "alice@example.com";
+	let server = srp_server(email, b"correct horse battery staple");
+
+	// in-process
+	for password in [&b"correct horse battery staple"[..], &b"hunter2"[..]].iter() {
+		let (mut client_ep, mut server_ep) = link::<SrpMessage>();
+		let srv = server.clone();
+		let handle = thread::spawn(move || server_login(&mut server_ep, &srv));
+		let client = SrpClient::new(SrpParams::nist(), email, password);
+		let login = client_login(&mut client_ep, &client);
+		drop(client_ep);
+		let accepted = handle.join().unwrap();
+		match (login, accepted) {
+			(Ok(key), Ok((_, server_key))) => {
+				assert_eq!(key, server_key);
+				println!("{}: logged in, K = {}", String::from_utf8_lossy(password), key);
+			},
+			(login, accepted) => println!("{}: refused, client: {}, server: {}", String::from_utf8_lossy(password),
+				login.unwrap_err(), accepted.unwrap_err()),
+		}
+	}
+
+	// the same over a local socket
+	let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+	let addr = listener.local_addr().unwrap();
+	let handle = thread::spawn(move || {
+		let (stream, _) = listener.accept().unwrap();
+		let mut transport = TcpTransport::new(stream).unwrap();
+		server_login(&mut transport, &server)
+	});
+	let mut transport = TcpTransport::new(TcpStream::connect(addr).unwrap()).unwrap();
+	let client = SrpClient::new(SrpParams::nist(), email, b"correct horse battery staple");
+	let key = client_login(&mut transport, &client).unwrap();
+	let (identity, server_key) = handle.join().unwrap().unwrap();
+	assert_eq!(key, server_key);
+	println!("{} logged in over TCP ({}), K = {}", identity, addr, key);
+}
+
+// Log in without the password by sending A = multiple * N,
+// the server then computes S = (A * v^u)^b = 0 mod N
+pub fn srp_zero_key_login<T: Transport<SrpMessage>>(transport: &mut T, params: &SrpParams, identity: &str, multiple: u32) -> Result<CryptoData, ProtocolError> {
+	let a = &params.n * &BigInt::from_u32(multiple);
+	transport.send(SrpMessage::Hello(identity.to_string(), a))?;
+	let salt = match transport.recv()? {
+		SrpMessage::Challenge(salt, _) => salt,
+		SrpMessage::Result(_) => return Err(ProtocolError::Crypto(CryptoError::Authentication)),
+		_ => return Err(ProtocolError::Unexpected("challenge")),
+	};
+
+	let key = session_key(&BigInt::zero());
+	transport.send(SrpMessage::Proof(proof(&key, &salt)))?;
+	match transport.recv()? {
+		SrpMessage::Result(true) => Ok(key),
+		SrpMessage::Result(false) => Err(ProtocolError::Crypto(CryptoError::Authentication)),
+		_ => Err(ProtocolError::Unexpected("result")),
+	}
 }
 
 // Break SRP with a zero key
 pub fn chal37() {
-	//TODO
+	let email = "alice@example.com";
+	let mut server = srp_server(email, b"correct horse battery staple");
+
+	for hardened in [false, true].iter() {
+		server.set_hardened(*hardened);
+		println!("{} server:", if *hardened { "hardened" } else { "naive" });
+
+		for multiple in 0..4 {
+			let (mut client_ep, mut server_ep) = link::<SrpMessage>();
+			let srv = server.clone();
+			let handle = thread::spawn(move || server_login(&mut server_ep, &srv));
+			let login = srp_zero_key_login(&mut client_ep, &SrpParams::nist(), email, multiple);
+			drop(client_ep);
+			let accepted = handle.join().unwrap();
+
+			assert_eq!(login.is_ok(), !*hardened);
+			match login {
+				Ok(key) => {
+					assert_eq!(key, accepted.unwrap().1);
+					println!("  A = {}N: logged in, K = {}", multiple, key);
+				},
+				Err(e) => println!("  A = {}N: {}, server: {}", multiple, e, accepted.unwrap_err()),
+			}
+		}
+
+		// and the real client still works
+		let (mut client_ep, mut server_ep) = link::<SrpMessage>();
+		let srv = server.clone();
+		let handle = thread::spawn(move || server_login(&mut server_ep, &srv));
+		let client = SrpClient::new(SrpParams::nist(), email, b"correct horse battery staple");
+		assert!(client_login(&mut client_ep, &client).is_ok());
+		drop(client_ep);
+		assert!(handle.join().unwrap().is_ok());
+	}
+}
+
+// What a fake simplified SRP server learns from one login attempt
+#[derive (Clone, Debug)]
+pub struct SimpleSrpCapture {
+	pub identity: String,
+	pub a: BigInt,
+	pub salt: CryptoData,
+	// Mallory's secret exponent, B = g^b
+	pub b: BigInt,
+	pub u: BigInt,
+	pub proof: CryptoData,
+}
+
+// Pose as the server with b = 1, u = 1 and record the client's proof.
+// The client can't tell: nothing it gets depends on the password.
+pub fn simple_srp_mitm<T: Transport<SrpMessage>>(transport: &mut T, params: &SrpParams) -> Result<SimpleSrpCapture, ProtocolError> {
+	let (identity, a) = match transport.recv()? {
+		SrpMessage::Hello(identity, a) => (identity, a),
+		_ => return Err(ProtocolError::Unexpected("hello")),
+	};
+
+	let salt = CryptoData::random(16);
+	let (b, u) = (BigInt::one(), BigInt::one());
+	transport.send(SrpMessage::SimpleChallenge(salt.clone(), params.g.modexp(&b, &params.n), u.clone()))?;
+
+	let proof = match transport.recv()? {
+		SrpMessage::Proof(proof) => proof,
+		_ => return Err(ProtocolError::Unexpected("proof")),
+	};
+	// looks like a mistyped password
+	transport.send(SrpMessage::Result(false))?;
+	Ok(SimpleSrpCapture { identity, a, salt, b, u, proof })
+}
+
+// whether password leads to the captured proof, S = (A * v^u)^b
+pub fn simple_srp_guess(params: &SrpParams, capture: &SimpleSrpCapture, password: &[u8]) -> bool {
+	let x = private_key(&capture.salt, password);
+	let v = params.g.modexp(&x, &params.n);
+	let s = (&capture.a * &v.modexp(&capture.u, &params.n)).modexp(&capture.b, &params.n);
+	proof(&session_key(&s), &capture.salt).ct_eq(&capture.proof)
+}
+
+pub fn read_wordlist(fname: &str) -> io::Result<Vec<String>> {
+	let contents = read_to_string(fname)?;
+	Ok(contents.lines().map(|w| w.trim()).filter(|w| !w.is_empty()).map(|w| w.to_string()).collect())
+}
+
+pub struct CrackReport {
+	pub password: Option<String>,
+	// candidates checked before stopping
+	pub tried: usize,
+	pub threads: usize,
+	// shares the wordlist was split into, one per thread started
+	pub chunks: usize,
+	pub elapsed: Duration,
+}
+
+impl CrackReport {
+	// candidates per second
+	pub fn throughput(&self) -> f64 {
+		self.tried as f64 / self.elapsed.as_secs_f64().max(1e-9)
+	}
+}
+
+// Check the words on all cores
+pub fn crack_simple_srp(params: &SrpParams, capture: &SimpleSrpCapture, words: &[String]) -> CrackReport {
+	let threads = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+	crack_simple_srp_threads(params, capture, words, threads)
+}
+
+// every thread takes a contiguous share of the words
+// and they all stop as soon as one finds the password
+pub fn crack_simple_srp_threads(params: &SrpParams, capture: &SimpleSrpCapture, words: &[String], threads: usize) -> CrackReport {
+	let threads = threads.max(1);
+	let share = words.len().div_ceil(threads).max(1);
+	let chunks = words.chunks(share).count();
+	let found = AtomicBool::new(false);
+	let tried = AtomicUsize::new(0);
+
+	let start = Instant::now();
+	let password = thread::scope(|scope| {
+		let workers: Vec<_> = words.chunks(share).map(|chunk| {
+			let (found, tried) = (&found, &tried);
+			scope.spawn(move || {
+				for word in chunk {
+					if found.load(Ordering::Relaxed) {
+						return None;
+					}
+					tried.fetch_add(1, Ordering::Relaxed);
+					if simple_srp_guess(params, capture, word.as_bytes()) {
+						found.store(true, Ordering::Relaxed);
+						return Some(word.clone());
+					}
+				}
+				None
+			})
+		}).collect();
+		workers.into_iter().filter_map(|w| w.join().unwrap()).next()
+	});
+
+	CrackReport { password, tried: tried.into_inner(), threads, chunks, elapsed: start.elapsed() }
 }
 
 // Offline dictionary attack on simplified SRP
 pub fn chal38() {
-	//TODO
+	let fname = "src/set5/38.txt";
+	let words = match read_wordlist(fname) {
+		Ok(ref words) if words.is_empty() => {
+			println!("no passwords in {}", fname);
+			return;
+		},
+		Ok(words) => words,
+		Err(e) => {
+			println!("can't read {}: {}", fname, e);
+			return;
+		},
+	};
+	let email = "alice@example.com";
+	let password = words[rand::rng().random_range(0..words.len())].clone();
+	let params = SrpParams::nist();
+
+	// the real server
+	let mut store = VerifierStore::new();
+	store.register(&params, email, password.as_bytes());
+	let server = SimpleSrpServer::new(params.clone(), store);
+	let (mut client_ep, mut server_ep) = link::<SrpMessage>();
+	let handle = thread::spawn(move || simple_server_login(&mut server_ep, &server));
+	let client = SrpClient::new(params.clone(), email, password.as_bytes());
+	let key = simple_client_login(&mut client_ep, &client).unwrap();
+	drop(client_ep);
+	assert_eq!(handle.join().unwrap().unwrap().1, key);
+	println!("simplified SRP login ok, K = {}", key);
+
+	// the client logs in to Mallory instead
+	let (mut client_ep, mut mallory_ep) = link::<SrpMessage>();
+	let mitm_params = params.clone();
+	let handle = thread::spawn(move || simple_srp_mitm(&mut mallory_ep, &mitm_params));
+	let client = SrpClient::new(params.clone(), email, password.as_bytes());
+	assert!(simple_client_login(&mut client_ep, &client).is_err());
+	drop(client_ep);
+	let capture = handle.join().unwrap().unwrap();
+	println!("captured proof of {}: {}", capture.identity, capture.proof);
+
+	let report = crack_simple_srp(&params, &capture, &words);
+	match report.password {
+		Some(ref found) => println!("password: {}", found),
+		None => println!("password not in {}", fname),
+	}
+	println!("{} of {} candidates in {:.2?} on {} threads ({} shares), {:.0} passwords/s",
+		report.tried, words.len(), report.elapsed, report.threads, report.chunks, report.throughput());
+	assert_eq!(report.password, Some(password));
 }
 
 // Implement RSA
@@ -40,3 +603,168 @@ pub fn chal40() {
 
 
 
+
+#[cfg(test)]
+mod tests {
+	use std::thread;
+
+	use set5::chals::{crack_simple_srp, crack_simple_srp_threads, echo_key_fixing, negotiated_g_injection,
+		simple_srp_guess, simple_srp_mitm, srp_zero_key_login, MaliciousG, SimpleSrpCapture};
+	use utils::bignum::BigInt;
+	use utils::cryptodata::{CryptoData, CryptoError};
+	use utils::dh::{DhGroup, DhKeyPair};
+	use utils::protocol::{link, ProtocolError};
+	use utils::srp::{client_login, server_login, simple_client_login, SrpClient, SrpMessage, SrpParams, SrpServer, VerifierStore};
+
+	const IDENTITY: &str = "alice@example.com";
+	const PASSWORD: &[u8] = b"correct horse battery staple";
+
+	fn server(hardened: bool) -> SrpServer {
+		let params = SrpParams::nist();
+		let mut store = VerifierStore::new();
+		store.register(&params, IDENTITY, PASSWORD);
+		let mut server = SrpServer::new(params, store);
+		server.set_hardened(hardened);
+		server
+	}
+
+	#[test]
+	fn echo_key_fixing_end_to_end() {
+		let msg = CryptoData::from_text("Cooking MC's like a pound of bacon");
+
+		let (alice, bob, seen) = echo_key_fixing(false, &msg);
+		assert_eq!(alice.unwrap(), msg);
+		assert_eq!(bob.unwrap(), msg);
+		assert_eq!(seen, vec![msg.clone(), msg.clone()]);
+
+		// validating the public keys stops it
+		let (alice, bob, seen) = echo_key_fixing(true, &msg);
+		assert!(alice.is_err());
+		assert!(bob.is_err());
+		assert!(seen.is_empty());
+	}
+
+	const INJECTIONS: [MaliciousG; 3] = [MaliciousG::One, MaliciousG::P, MaliciousG::PMinusOne];
+
+	#[test]
+	fn g_injection_end_to_end() {
+		let msg = CryptoData::from_text("Cooking MC's like a pound of bacon");
+
+		for &injection in INJECTIONS.iter() {
+			let (alice, bob, seen) = negotiated_g_injection(injection, false, &msg);
+			assert_eq!(alice.unwrap(), msg);
+			assert_eq!(bob.unwrap(), msg);
+			// Alice's message and Bob's echo
+			assert_eq!(seen.len(), 2);
+			for plain in seen {
+				assert_eq!(plain.unwrap(), msg);
+			}
+
+			// the degenerate public keys are refused
+			let (alice, bob, seen) = negotiated_g_injection(injection, true, &msg);
+			assert!(alice.is_err());
+			assert!(matches!(bob, Err(ProtocolError::Crypto(CryptoError::PublicKey))));
+			assert!(seen.is_empty());
+		}
+	}
+
+	#[test]
+	fn g_injection_predictions() {
+		let p = DhGroup::nist().p;
+		for &injection in INJECTIONS.iter() {
+			let group = DhGroup::new(p.clone(), injection.value(&p));
+			// odd and even private keys, for g = p - 1 the secret is
+			// p - 1 only if both are odd
+			for &(x, y) in [(3, 5), (3, 4), (4, 5), (4, 6)].iter() {
+				let mut a = DhKeyPair::from_private(group.clone(), BigInt::from_u32(x));
+				let b = DhKeyPair::from_private(group.clone(), BigInt::from_u32(y));
+				a.set_validation(false);
+				let secret = a.shared_secret(b.public()).unwrap();
+				assert_eq!(injection.predict_secret(&p, a.public(), b.public()), secret);
+				if injection == MaliciousG::PMinusOne {
+					assert_eq!(secret.is_one(), x % 2 == 0 || y % 2 == 0);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn zero_key_over_protocol() {
+		for &hardened in [false, true].iter() {
+			for multiple in 0..3 {
+				let (mut client_ep, mut server_ep) = link::<SrpMessage>();
+				let srv = server(hardened);
+				let handle = thread::spawn(move || server_login(&mut server_ep, &srv));
+				let login = srp_zero_key_login(&mut client_ep, &SrpParams::nist(), IDENTITY, multiple);
+				drop(client_ep);
+				let accepted = handle.join().unwrap();
+
+				if hardened {
+					assert!(login.is_err());
+					assert!(matches!(accepted, Err(ProtocolError::Crypto(CryptoError::PublicKey))));
+				} else {
+					let (identity, key) = accepted.unwrap();
+					assert_eq!(identity, IDENTITY);
+					assert_eq!(login.unwrap(), key);
+				}
+			}
+
+			// the real client still gets in
+			let (mut client_ep, mut server_ep) = link::<SrpMessage>();
+			let srv = server(hardened);
+			let handle = thread::spawn(move || server_login(&mut server_ep, &srv));
+			let client = SrpClient::new(SrpParams::nist(), IDENTITY, PASSWORD);
+			let key = client_login(&mut client_ep, &client).unwrap();
+			drop(client_ep);
+			assert_eq!(handle.join().unwrap().unwrap().1, key);
+		}
+	}
+
+	// the client logs in to Mallory posing as a simplified SRP server
+	fn capture(password: &str) -> SimpleSrpCapture {
+		let (mut client_ep, mut mallory_ep) = link::<SrpMessage>();
+		let handle = thread::spawn(move || simple_srp_mitm(&mut mallory_ep, &SrpParams::nist()));
+		let client = SrpClient::new(SrpParams::nist(), IDENTITY, password.as_bytes());
+		assert!(matches!(simple_client_login(&mut client_ep, &client), Err(ProtocolError::Crypto(CryptoError::Authentication))));
+		drop(client_ep);
+		handle.join().unwrap().unwrap()
+	}
+
+	fn wordlist() -> Vec<String> {
+		(0..40).map(|i| format!("password{}", i)).collect()
+	}
+
+	#[test]
+	fn simple_srp_crack() {
+		let words = wordlist();
+		let capture = capture("password29");
+		assert_eq!(capture.identity, IDENTITY);
+		assert!(simple_srp_guess(&SrpParams::nist(), &capture, b"password29"));
+		assert!(!simple_srp_guess(&SrpParams::nist(), &capture, b"password28"));
+
+		let report = crack_simple_srp_threads(&SrpParams::nist(), &capture, &words, 4);
+		assert_eq!(report.password, Some("password29".to_string()));
+		assert!(0 < report.tried && report.tried <= words.len());
+		assert_eq!(report.threads, 4);
+		assert_eq!(report.chunks, 4);
+
+		let report = crack_simple_srp(&SrpParams::nist(), &capture, &words);
+		assert_eq!(report.password, Some("password29".to_string()));
+	}
+
+	#[test]
+	fn simple_srp_crack_misses() {
+		let words = wordlist();
+		let capture = capture("hunter2");
+		let report = crack_simple_srp_threads(&SrpParams::nist(), &capture, &words, 3);
+		assert_eq!(report.password, None);
+		// nothing stops the search early
+		assert_eq!(report.tried, words.len());
+		assert_eq!(report.chunks, 3);
+
+		let report = crack_simple_srp_threads(&SrpParams::nist(), &capture, &[], 3);
+		assert_eq!(report.password, None);
+		assert_eq!(report.tried, 0);
+		assert_eq!(report.chunks, 0);
+	}
+}
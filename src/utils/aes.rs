@@ -0,0 +1,394 @@
+use utils::blockcipher::BlockCipher;
+use utils::cryptodata::{xor_slice, CryptoError};
+
+pub const BLOCK_SIZE: usize = 16;
+
+pub type State = [u8; 16];
+
+pub const SBOX: [u8; 256] = [
+	0x63, 0x7c, 0x77, 0x7b, 0xf2, 0x6b, 0x6f, 0xc5, 0x30, 0x01, 0x67, 0x2b, 0xfe, 0xd7, 0xab, 0x76,
+	0xca, 0x82, 0xc9, 0x7d, 0xfa, 0x59, 0x47, 0xf0, 0xad, 0xd4, 0xa2, 0xaf, 0x9c, 0xa4, 0x72, 0xc0,
+	0xb7, 0xfd, 0x93, 0x26, 0x36, 0x3f, 0xf7, 0xcc, 0x34, 0xa5, 0xe5, 0xf1, 0x71, 0xd8, 0x31, 0x15,
+	0x04, 0xc7, 0x23, 0xc3, 0x18, 0x96, 0x05, 0x9a, 0x07, 0x12, 0x80, 0xe2, 0xeb, 0x27, 0xb2, 0x75,
+	0x09, 0x83, 0x2c, 0x1a, 0x1b, 0x6e, 0x5a, 0xa0, 0x52, 0x3b, 0xd6, 0xb3, 0x29, 0xe3, 0x2f, 0x84,
+	0x53, 0xd1, 0x00, 0xed, 0x20, 0xfc, 0xb1, 0x5b, 0x6a, 0xcb, 0xbe, 0x39, 0x4a, 0x4c, 0x58, 0xcf,
+	0xd0, 0xef, 0xaa, 0xfb, 0x43, 0x4d, 0x33, 0x85, 0x45, 0xf9, 0x02, 0x7f, 0x50, 0x3c, 0x9f, 0xa8,
+	0x51, 0xa3, 0x40, 0x8f, 0x92, 0x9d, 0x38, 0xf5, 0xbc, 0xb6, 0xda, 0x21, 0x10, 0xff, 0xf3, 0xd2,
+	0xcd, 0x0c, 0x13, 0xec, 0x5f, 0x97, 0x44, 0x17, 0xc4, 0xa7, 0x7e, 0x3d, 0x64, 0x5d, 0x19, 0x73,
+	0x60, 0x81, 0x4f, 0xdc, 0x22, 0x2a, 0x90, 0x88, 0x46, 0xee, 0xb8, 0x14, 0xde, 0x5e, 0x0b, 0xdb,
+	0xe0, 0x32, 0x3a, 0x0a, 0x49, 0x06, 0x24, 0x5c, 0xc2, 0xd3, 0xac, 0x62, 0x91, 0x95, 0xe4, 0x79,
+	0xe7, 0xc8, 0x37, 0x6d, 0x8d, 0xd5, 0x4e, 0xa9, 0x6c, 0x56, 0xf4, 0xea, 0x65, 0x7a, 0xae, 0x08,
+	0xba, 0x78, 0x25, 0x2e, 0x1c, 0xa6, 0xb4, 0xc6, 0xe8, 0xdd, 0x74, 0x1f, 0x4b, 0xbd, 0x8b, 0x8a,
+	0x70, 0x3e, 0xb5, 0x66, 0x48, 0x03, 0xf6, 0x0e, 0x61, 0x35, 0x57, 0xb9, 0x86, 0xc1, 0x1d, 0x9e,
+	0xe1, 0xf8, 0x98, 0x11, 0x69, 0xd9, 0x8e, 0x94, 0x9b, 0x1e, 0x87, 0xe9, 0xce, 0x55, 0x28, 0xdf,
+	0x8c, 0xa1, 0x89, 0x0d, 0xbf, 0xe6, 0x42, 0x68, 0x41, 0x99, 0x2d, 0x0f, 0xb0, 0x54, 0xbb, 0x16,
+];
+
+pub const INV_SBOX: [u8; 256] = [
+	0x52, 0x09, 0x6a, 0xd5, 0x30, 0x36, 0xa5, 0x38, 0xbf, 0x40, 0xa3, 0x9e, 0x81, 0xf3, 0xd7, 0xfb,
+	0x7c, 0xe3, 0x39, 0x82, 0x9b, 0x2f, 0xff, 0x87, 0x34, 0x8e, 0x43, 0x44, 0xc4, 0xde, 0xe9, 0xcb,
+	0x54, 0x7b, 0x94, 0x32, 0xa6, 0xc2, 0x23, 0x3d, 0xee, 0x4c, 0x95, 0x0b, 0x42, 0xfa, 0xc3, 0x4e,
+	0x08, 0x2e, 0xa1, 0x66, 0x28, 0xd9, 0x24, 0xb2, 0x76, 0x5b, 0xa2, 0x49, 0x6d, 0x8b, 0xd1, 0x25,
+	0x72, 0xf8, 0xf6, 0x64, 0x86, 0x68, 0x98, 0x16, 0xd4, 0xa4, 0x5c, 0xcc, 0x5d, 0x65, 0xb6, 0x92,
+	0x6c, 0x70, 0x48, 0x50, 0xfd, 0xed, 0xb9, 0xda, 0x5e, 0x15, 0x46, 0x57, 0xa7, 0x8d, 0x9d, 0x84,
+	0x90, 0xd8, 0xab, 0x00, 0x8c, 0xbc, 0xd3, 0x0a, 0xf7, 0xe4, 0x58, 0x05, 0xb8, 0xb3, 0x45, 0x06,
+	0xd0, 0x2c, 0x1e, 0x8f, 0xca, 0x3f, 0x0f, 0x02, 0xc1, 0xaf, 0xbd, 0x03, 0x01, 0x13, 0x8a, 0x6b,
+	0x3a, 0x91, 0x11, 0x41, 0x4f, 0x67, 0xdc, 0xea, 0x97, 0xf2, 0xcf, 0xce, 0xf0, 0xb4, 0xe6, 0x73,
+	0x96, 0xac, 0x74, 0x22, 0xe7, 0xad, 0x35, 0x85, 0xe2, 0xf9, 0x37, 0xe8, 0x1c, 0x75, 0xdf, 0x6e,
+	0x47, 0xf1, 0x1a, 0x71, 0x1d, 0x29, 0xc5, 0x89, 0x6f, 0xb7, 0x62, 0x0e, 0xaa, 0x18, 0xbe, 0x1b,
+	0xfc, 0x56, 0x3e, 0x4b, 0xc6, 0xd2, 0x79, 0x20, 0x9a, 0xdb, 0xc0, 0xfe, 0x78, 0xcd, 0x5a, 0xf4,
+	0x1f, 0xdd, 0xa8, 0x33, 0x88, 0x07, 0xc7, 0x31, 0xb1, 0x12, 0x10, 0x59, 0x27, 0x80, 0xec, 0x5f,
+	0x60, 0x51, 0x7f, 0xa9, 0x19, 0xb5, 0x4a, 0x0d, 0x2d, 0xe5, 0x7a, 0x9f, 0x93, 0xc9, 0x9c, 0xef,
+	0xa0, 0xe0, 0x3b, 0x4d, 0xae, 0x2a, 0xf5, 0xb0, 0xc8, 0xeb, 0xbb, 0x3c, 0x83, 0x53, 0x99, 0x61,
+	0x17, 0x2b, 0x04, 0x7e, 0xba, 0x77, 0xd6, 0x26, 0xe1, 0x69, 0x14, 0x63, 0x55, 0x21, 0x0c, 0x7d,
+];
+
+// round constants for the key schedule
+const RCON: [u8; 10] = [0x01, 0x02, 0x04, 0x08, 0x10, 0x20, 0x40, 0x80, 0x1b, 0x36];
+
+#[derive (Clone,Copy,Debug,PartialEq,Eq)]
+pub enum AesVariant {
+	Aes128,
+	Aes192,
+	Aes256,
+}
+
+impl AesVariant {
+	pub fn from_key_len(key_len: usize) -> Result<AesVariant, CryptoError> {
+		match key_len {
+			16 => Ok(AesVariant::Aes128),
+			24 => Ok(AesVariant::Aes192),
+			32 => Ok(AesVariant::Aes256),
+			_ => Err(CryptoError::KeyLength(key_len)),
+		}
+	}
+
+	pub fn key_len(&self) -> usize {
+		match *self {
+			AesVariant::Aes128 => 16,
+			AesVariant::Aes192 => 24,
+			AesVariant::Aes256 => 32,
+		}
+	}
+
+	pub fn rounds(&self) -> usize {
+		self.key_len() / 4 + 6
+	}
+}
+
+// Native AES-128/192/256
+//
+// The state is a 16 byte array in the FIPS-197 column-major order,
+// which is the same as the order of the input block.
+#[derive (Clone)]
+pub struct Aes {
+	variant: AesVariant,
+	round_keys: Vec<State>,
+}
+
+// number of rounds for the given key length
+pub fn rounds_for_key(key_len: usize) -> Result<usize, CryptoError> {
+	Ok(AesVariant::from_key_len(key_len)?.rounds())
+}
+
+fn sub_word(w: [u8; 4]) -> [u8; 4] {
+	[SBOX[w[0] as usize], SBOX[w[1] as usize], SBOX[w[2] as usize], SBOX[w[3] as usize]]
+}
+
+// expand the key into rounds + 1 round keys
+pub fn key_expansion(key: &[u8]) -> Result<Vec<State>, CryptoError> {
+	let rounds = rounds_for_key(key.len())?;
+	let nk = key.len() / 4;
+	let total = 4 * (rounds + 1);
+
+	let mut words: Vec<[u8; 4]> = Vec::with_capacity(total);
+	for i in 0..nk {
+		words.push([key[4 * i], key[4 * i + 1], key[4 * i + 2], key[4 * i + 3]]);
+	}
+
+	for i in nk..total {
+		let mut temp = words[i - 1];
+		if i % nk == 0 {
+			// RotWord, SubWord, Rcon
+			temp = sub_word([temp[1], temp[2], temp[3], temp[0]]);
+			temp[0] ^= RCON[i / nk - 1];
+		} else if nk > 6 && i % nk == 4 {
+			temp = sub_word(temp);
+		}
+		let prev = words[i - nk];
+		words.push([prev[0] ^ temp[0], prev[1] ^ temp[1], prev[2] ^ temp[2], prev[3] ^ temp[3]]);
+	}
+
+	let mut round_keys = Vec::with_capacity(rounds + 1);
+	for chunk in words.chunks(4) {
+		let mut rk = [0u8; 16];
+		for (c, w) in chunk.iter().enumerate() {
+			rk[4 * c..4 * c + 4].copy_from_slice(w);
+		}
+		round_keys.push(rk);
+	}
+	Ok(round_keys)
+}
+
+// multiplication by x in GF(2^8)
+pub fn xtime(b: u8) -> u8 {
+	(b << 1) ^ if b & 0x80 != 0 { 0x1b } else { 0 }
+}
+
+// multiplication in GF(2^8)
+pub fn gmul(mut a: u8, mut b: u8) -> u8 {
+	let mut res = 0;
+	while b != 0 {
+		if b & 1 != 0 {
+			res ^= a;
+		}
+		a = xtime(a);
+		b >>= 1;
+	}
+	res
+}
+
+pub fn add_round_key(state: &mut State, round_key: &State) {
+	xor_slice(state, round_key);
+}
+
+pub fn sub_bytes(state: &mut State) {
+	for s in state.iter_mut() {
+		*s = SBOX[*s as usize];
+	}
+}
+
+pub fn inv_sub_bytes(state: &mut State) {
+	for s in state.iter_mut() {
+		*s = INV_SBOX[*s as usize];
+	}
+}
+
+// row r is rotated left by r positions
+pub fn shift_rows(state: &mut State) {
+	let old = *state;
+	for r in 0..4 {
+		for c in 0..4 {
+			state[r + 4 * c] = old[r + 4 * ((c + r) % 4)];
+		}
+	}
+}
+
+pub fn inv_shift_rows(state: &mut State) {
+	let old = *state;
+	for r in 0..4 {
+		for c in 0..4 {
+			state[r + 4 * ((c + r) % 4)] = old[r + 4 * c];
+		}
+	}
+}
+
+pub fn mix_columns(state: &mut State) {
+	for col in state.chunks_mut(4) {
+		let a = [col[0], col[1], col[2], col[3]];
+		let all = a[0] ^ a[1] ^ a[2] ^ a[3];
+		for i in 0..4 {
+			// 2*a_i ^ 3*a_{i+1} ^ a_{i+2} ^ a_{i+3}
+			col[i] = a[i] ^ all ^ xtime(a[i] ^ a[(i + 1) % 4]);
+		}
+	}
+}
+
+pub fn inv_mix_columns(state: &mut State) {
+	for col in state.chunks_mut(4) {
+		let a = [col[0], col[1], col[2], col[3]];
+		for i in 0..4 {
+			col[i] = gmul(a[i], 14) ^ gmul(a[(i + 1) % 4], 11)
+				^ gmul(a[(i + 2) % 4], 13) ^ gmul(a[(i + 3) % 4], 9);
+		}
+	}
+}
+
+// one full encryption round
+pub fn encrypt_round(state: &mut State, round_key: &State) {
+	sub_bytes(state);
+	shift_rows(state);
+	mix_columns(state);
+	add_round_key(state, round_key);
+}
+
+// last encryption round, without MixColumns
+pub fn encrypt_final_round(state: &mut State, round_key: &State) {
+	sub_bytes(state);
+	shift_rows(state);
+	add_round_key(state, round_key);
+}
+
+// inverse of encrypt_round()
+pub fn decrypt_round(state: &mut State, round_key: &State) {
+	add_round_key(state, round_key);
+	inv_mix_columns(state);
+	inv_shift_rows(state);
+	inv_sub_bytes(state);
+}
+
+// inverse of encrypt_final_round()
+pub fn decrypt_final_round(state: &mut State, round_key: &State) {
+	add_round_key(state, round_key);
+	inv_shift_rows(state);
+	inv_sub_bytes(state);
+}
+
+impl Aes {
+	// the variant is picked from the key length
+	pub fn new(key: &[u8]) -> Result<Aes, CryptoError> {
+		Ok(Aes {
+			variant: AesVariant::from_key_len(key.len())?,
+			round_keys: key_expansion(key)?,
+		})
+	}
+
+	// fails if the key length doesn't match the variant
+	pub fn with_variant(variant: AesVariant, key: &[u8]) -> Result<Aes, CryptoError> {
+		if key.len() != variant.key_len() {
+			return Err(CryptoError::KeyLength(key.len()));
+		}
+		Aes::new(key)
+	}
+
+	pub fn variant(&self) -> AesVariant {
+		self.variant
+	}
+
+	pub fn rounds(&self) -> usize {
+		self.round_keys.len() - 1
+	}
+
+	pub fn round_keys(&self) -> &[State] {
+		&self.round_keys
+	}
+
+	// encrypt with only the first `rounds` rounds,
+	// the last of them is a final round (no MixColumns)
+	pub fn encrypt_rounds(&self, state: &mut State, rounds: usize) {
+		assert!(0 < rounds && rounds <= self.rounds());
+		add_round_key(state, &self.round_keys[0]);
+		for r in 1..rounds {
+			encrypt_round(state, &self.round_keys[r]);
+		}
+		encrypt_final_round(state, &self.round_keys[rounds]);
+	}
+
+	// inverse of encrypt_rounds()
+	pub fn decrypt_rounds(&self, state: &mut State, rounds: usize) {
+		assert!(0 < rounds && rounds <= self.rounds());
+		decrypt_final_round(state, &self.round_keys[rounds]);
+		for r in (1..rounds).rev() {
+			decrypt_round(state, &self.round_keys[r]);
+		}
+		add_round_key(state, &self.round_keys[0]);
+	}
+
+	pub fn encrypt_state(&self, state: &mut State) {
+		let rounds = self.rounds();
+		self.encrypt_rounds(state, rounds);
+	}
+
+	pub fn decrypt_state(&self, state: &mut State) {
+		let rounds = self.rounds();
+		self.decrypt_rounds(state, rounds);
+	}
+}
+
+impl BlockCipher for Aes {
+	fn block_size(&self) -> usize {
+		BLOCK_SIZE
+	}
+
+	fn encrypt_block(&self, block: &mut [u8]) {
+		let mut state = [0u8; 16];
+		state.copy_from_slice(block);
+		self.encrypt_state(&mut state);
+		block.copy_from_slice(&state);
+	}
+
+	fn decrypt_block(&self, block: &mut [u8]) {
+		let mut state = [0u8; 16];
+		state.copy_from_slice(block);
+		self.decrypt_state(&mut state);
+		block.copy_from_slice(&state);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use openssl::symm::{Cipher, Crypter, Mode};
+
+	use utils::aes::{Aes, AesVariant};
+	use utils::blockcipher::BlockCipher;
+	use utils::cryptodata::{CryptoData, CryptoError};
+
+	// FIPS-197 Appendix C
+	const PLAIN: &str = "00112233445566778899aabbccddeeff";
+
+	fn check_fips197(key: &str, expected: &str) {
+		let aes = Aes::new(&CryptoData::from_hex(key)).unwrap();
+		let mut block = CryptoData::from_hex(PLAIN).into_vec();
+		aes.encrypt_block(&mut block);
+		assert_eq!(CryptoData::from_owned(block.clone()).to_hex(), expected);
+		aes.decrypt_block(&mut block);
+		assert_eq!(CryptoData::from_owned(block).to_hex(), PLAIN);
+	}
+
+	#[test]
+	fn fips197_aes128() {
+		check_fips197("000102030405060708090a0b0c0d0e0f", "69c4e0d86a7b0430d8cdb78070b4c55a");
+	}
+
+	#[test]
+	fn fips197_aes192() {
+		check_fips197("000102030405060708090a0b0c0d0e0f1011121314151617", "dda97ca4864cdfe06eaf70a0ec0d7191");
+	}
+
+	#[test]
+	fn fips197_aes256() {
+		check_fips197("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f", "8ea2b7ca516745bfeafc49904b496089");
+	}
+
+	fn openssl_ecb(cipher: Cipher, key: &[u8], data: &[u8]) -> Vec<u8> {
+		let mut crypter = Crypter::new(cipher, Mode::Encrypt, key, None).unwrap();
+		crypter.pad(false);
+		let mut out = vec![0u8; data.len() + cipher.block_size()];
+		let count = crypter.update(data, &mut out).unwrap();
+		let rest = crypter.finalize(&mut out[count..]).unwrap();
+		out.truncate(count + rest);
+		out
+	}
+
+	#[test]
+	fn matches_openssl() {
+		let ciphers = [(16, Cipher::aes_128_ecb()), (24, Cipher::aes_192_ecb()), (32, Cipher::aes_256_ecb())];
+		for &(key_len, cipher) in ciphers.iter() {
+			for _ in 0..32 {
+				let key = CryptoData::random(key_len);
+				let plain = CryptoData::random(16);
+				let aes = Aes::new(&key).unwrap();
+
+				let mut block = plain.clone().into_vec();
+				aes.encrypt_block(&mut block);
+				assert_eq!(block, openssl_ecb(cipher, &key, &plain));
+				aes.decrypt_block(&mut block);
+				assert_eq!(&block[..], &plain[..]);
+			}
+
+			// the AES modes in CryptoData go through Aes::new
+			let key = CryptoData::random(key_len);
+			let plain = CryptoData::random(4 * 16);
+			assert_eq!(plain.ECB_encrypt(&key).into_vec(), openssl_ecb(cipher, &key, &plain));
+		}
+	}
+
+	#[test]
+	fn key_length() {
+		assert!(match Aes::new(&CryptoData::zero(20)) {
+			Err(CryptoError::KeyLength(20)) => true,
+			_ => false,
+		});
+		assert!(Aes::with_variant(AesVariant::Aes256, &CryptoData::zero(16)).is_err());
+	}
+}
@@ -0,0 +1,394 @@
+// Arbitrary precision integers on top of OpenSSL's BigNum.
+// OpenSSL only fails here on allocation errors or division by zero,
+// so the arithmetic panics instead of returning a Result.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Div, Mul, Rem, Shl, Shr, Sub};
+
+use openssl::bn::{BigNum, BigNumContext, BigNumRef};
+
+use utils::cryptodata::{CryptoData, CryptoError};
+
+pub struct BigInt {
+	n: BigNum,
+}
+
+fn ctx() -> BigNumContext {
+	BigNumContext::new().expect("BigNumContext allocation failed")
+}
+
+fn new_bn() -> BigNum {
+	BigNum::new().expect("BigNum allocation failed")
+}
+
+impl BigInt {
+	fn wrap(n: BigNum) -> BigInt {
+		BigInt { n }
+	}
+
+	pub fn zero() -> BigInt {
+		BigInt::from_u32(0)
+	}
+
+	pub fn one() -> BigInt {
+		BigInt::from_u32(1)
+	}
+
+	pub fn from_u32(n: u32) -> BigInt {
+		BigInt::wrap(BigNum::from_u32(n).expect("BigNum allocation failed"))
+	}
+
+	pub fn from_u64(n: u64) -> BigInt {
+		BigInt::from_bytes(&n.to_be_bytes())
+	}
+
+	// unsigned big-endian
+	pub fn from_bytes(bytes: &[u8]) -> BigInt {
+		BigInt::wrap(BigNum::from_slice(bytes).expect("BigNum allocation failed"))
+	}
+
+	pub fn from_hex(hexstring: &str) -> BigInt {
+		BigInt::try_from_hex(hexstring).unwrap()
+	}
+
+	// whitespace is ignored, so constants can be split over lines
+	pub fn try_from_hex(hexstring: &str) -> Result<BigInt, CryptoError> {
+		let mut digits: String = hexstring.split_whitespace().collect();
+		if digits.len() % 2 == 1 {
+			digits.insert(0, '0');
+		}
+		let bytes = CryptoData::try_from_hex(&digits)?;
+		Ok(BigInt::from_bytes(&bytes))
+	}
+
+	pub fn from_dec(decstring: &str) -> BigInt {
+		BigInt::wrap(BigNum::from_dec_str(decstring).expect("invalid decimal number"))
+	}
+
+	// uniformly random in 0..limit
+	pub fn random_below(limit: &BigInt) -> BigInt {
+		let mut r = new_bn();
+		limit.n.rand_range(&mut r).expect("random number generation failed");
+		BigInt::wrap(r)
+	}
+
+	// uniformly random in low..high
+	pub fn random_range(low: &BigInt, high: &BigInt) -> BigInt {
+		let width = high - low;
+		low + &BigInt::random_below(&width)
+	}
+
+	pub fn as_bignum(&self) -> &BigNumRef {
+		&self.n
+	}
+
+	// unsigned big-endian without leading zeros, zero is empty
+	pub fn to_bytes(&self) -> CryptoData {
+		CryptoData::from_owned(self.n.to_vec())
+	}
+
+	// big-endian left padded with zeros to len bytes
+	pub fn to_bytes_padded(&self, len: usize) -> CryptoData {
+		CryptoData::from_owned(self.n.to_vec_padded(len as i32).expect("number too long for padding"))
+	}
+
+	// lowercase without leading zeros
+	pub fn to_hex(&self) -> String {
+		// OpenSSL prints whole bytes
+		let hex = self.n.to_hex_str().expect("BigNum conversion failed").to_lowercase();
+		let (sign, digits) = match hex.starts_with('-') {
+			true => ("-", &hex[1..]),
+			false => ("", &hex[..]),
+		};
+		match digits.trim_start_matches('0') {
+			"" => "0".to_string(),
+			digits => format!("{}{}", sign, digits),
+		}
+	}
+
+	pub fn to_dec(&self) -> String {
+		self.n.to_dec_str().expect("BigNum conversion failed").to_string()
+	}
+
+	pub fn bits(&self) -> usize {
+		self.n.num_bits() as usize
+	}
+
+	pub fn is_zero(&self) -> bool {
+		self.n.num_bits() == 0
+	}
+
+	pub fn is_one(&self) -> bool {
+		*self == BigInt::one()
+	}
+
+	pub fn is_negative(&self) -> bool {
+		self.n.is_negative()
+	}
+
+	pub fn is_even(&self) -> bool {
+		self.n.is_even()
+	}
+
+	pub fn pow(&self, e: u32) -> BigInt {
+		let mut r = new_bn();
+		let e = BigNum::from_u32(e).expect("BigNum allocation failed");
+		r.exp(&self.n, &e, &mut ctx()).expect("exponentiation failed");
+		BigInt::wrap(r)
+	}
+
+	// non-negative remainder, unlike %
+	pub fn modulo(&self, m: &BigInt) -> BigInt {
+		let mut r = new_bn();
+		r.nnmod(&self.n, &m.n, &mut ctx()).expect("modulo failed");
+		BigInt::wrap(r)
+	}
+
+	// self^e mod m
+	pub fn modexp(&self, e: &BigInt, m: &BigInt) -> BigInt {
+		let mut r = new_bn();
+		r.mod_exp(&self.n, &e.n, &m.n, &mut ctx()).expect("modular exponentiation failed");
+		BigInt::wrap(r)
+	}
+
+	// x with self * x = 1 mod m, None if gcd(self, m) != 1
+	pub fn modinv(&self, m: &BigInt) -> Option<BigInt> {
+		let mut r = new_bn();
+		match r.mod_inverse(&self.modulo(m).n, &m.n, &mut ctx()) {
+			Ok(()) => Some(BigInt::wrap(r)),
+			Err(_) => None,
+		}
+	}
+
+	pub fn gcd(&self, other: &BigInt) -> BigInt {
+		let mut r = new_bn();
+		r.gcd(&self.n, &other.n, &mut ctx()).expect("gcd failed");
+		BigInt::wrap(r)
+	}
+
+	// floor of the n-th root of a non-negative number
+	pub fn nth_root(&self, n: u32) -> BigInt {
+		assert!(n > 0 && !self.is_negative());
+		if self.is_zero() || n == 1 {
+			return self.clone();
+		}
+
+		// Newton's method from above, 2^ceil(bits/n) is always >= the root
+		let n_big = BigInt::from_u32(n);
+		let n_1 = BigInt::from_u32(n - 1);
+		let mut x = &BigInt::one() << self.bits().div_ceil(n as usize);
+		loop {
+			let y = &(&(&n_1 * &x) + &(self / &x.pow(n - 1))) / &n_big;
+			if y >= x {
+				return x;
+			}
+			x = y;
+		}
+	}
+}
+
+// Chinese remainder theorem: x with x = a_i mod n_i for every (a_i, n_i),
+// reduced modulo the product of the n_i. None if the moduli aren't coprime.
+pub fn crt(congruences: &[(BigInt, BigInt)]) -> Option<BigInt> {
+	let n = congruences.iter().fold(BigInt::one(), |acc, (_, ni)| &acc * ni);
+
+	let mut x = BigInt::zero();
+	for (ai, ni) in congruences {
+		let ms = &n / ni;
+		let inv = ms.modinv(ni)?;
+		x = &x + &(&(ai * &ms) * &inv);
+	}
+	Some(x.modulo(&n))
+}
+
+impl Clone for BigInt {
+	fn clone(&self) -> BigInt {
+		BigInt::wrap(self.n.to_owned().expect("BigNum allocation failed"))
+	}
+}
+
+impl PartialEq for BigInt {
+	fn eq(&self, other: &BigInt) -> bool {
+		self.n == other.n
+	}
+}
+
+impl Eq for BigInt {}
+
+impl PartialOrd for BigInt {
+	fn partial_cmp(&self, other: &BigInt) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl Ord for BigInt {
+	fn cmp(&self, other: &BigInt) -> Ordering {
+		self.n.cmp(&other.n)
+	}
+}
+
+impl fmt::Display for BigInt {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}", self.to_hex())
+	}
+}
+
+impl fmt::Debug for BigInt {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "BigInt({})", self.to_hex())
+	}
+}
+
+impl From<u32> for BigInt {
+	fn from(n: u32) -> BigInt {
+		BigInt::from_u32(n)
+	}
+}
+
+impl From<BigNum> for BigInt {
+	fn from(n: BigNum) -> BigInt {
+		BigInt::wrap(n)
+	}
+}
+
+impl From<&CryptoData> for BigInt {
+	fn from(data: &CryptoData) -> BigInt {
+		BigInt::from_bytes(data)
+	}
+}
+
+impl From<&BigInt> for CryptoData {
+	fn from(n: &BigInt) -> CryptoData {
+		n.to_bytes()
+	}
+}
+
+// arithmetic operators for references and owned values
+macro_rules! bigint_op {
+	($trait:ident, $method:ident) => {
+		impl<'a, 'b> $trait<&'b BigInt> for &'a BigInt {
+			type Output = BigInt;
+
+			fn $method(self, other: &BigInt) -> BigInt {
+				let r: &BigNumRef = &self.n;
+				BigInt::wrap(r.$method(&*other.n))
+			}
+		}
+
+		impl $trait for BigInt {
+			type Output = BigInt;
+
+			fn $method(self, other: BigInt) -> BigInt {
+				(&self).$method(&other)
+			}
+		}
+	};
+}
+
+bigint_op!(Add, add);
+bigint_op!(Sub, sub);
+bigint_op!(Mul, mul);
+bigint_op!(Div, div);
+bigint_op!(Rem, rem);
+
+impl Shl<usize> for &BigInt {
+	type Output = BigInt;
+
+	fn shl(self, bits: usize) -> BigInt {
+		BigInt::wrap(&self.n << bits as i32)
+	}
+}
+
+impl Shr<usize> for &BigInt {
+	type Output = BigInt;
+
+	fn shr(self, bits: usize) -> BigInt {
+		BigInt::wrap(&self.n >> bits as i32)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use utils::bignum::{crt, BigInt};
+
+	fn big(n: u32) -> BigInt {
+		BigInt::from_u32(n)
+	}
+
+	#[test]
+	fn modinv() {
+		assert_eq!(big(17).modinv(&big(3120)), Some(big(2753)));
+		assert_eq!(big(3).modinv(&big(11)), Some(big(4)));
+		// reduced first
+		assert_eq!(big(14).modinv(&big(11)), Some(big(4)));
+		assert_eq!(big(6).modinv(&big(9)), None);
+		assert_eq!(big(0).modinv(&big(7)), None);
+	}
+
+	#[test]
+	fn crt_small_system() {
+		let system = [(big(2), big(3)), (big(3), big(5)), (big(2), big(7))];
+		assert_eq!(crt(&system), Some(big(23)));
+		// the a_i don't have to be reduced
+		let system = [(big(5), big(3)), (big(13), big(5))];
+		assert_eq!(crt(&system), Some(big(8)));
+		assert_eq!(crt(&[(big(1), big(4)), (big(3), big(6))]), None);
+	}
+
+	#[test]
+	fn nth_root() {
+		assert_eq!(big(0).nth_root(3), big(0));
+		assert_eq!(big(1).nth_root(3), big(1));
+		assert_eq!(big(27).nth_root(3), big(3));
+		assert_eq!(big(26).nth_root(3), big(2));
+		assert_eq!(big(28).nth_root(3), big(3));
+		assert_eq!(big(12345).nth_root(1), big(12345));
+
+		let x = &(&BigInt::one() << 100) + &big(1);
+		let cube = x.pow(3);
+		assert_eq!(cube.nth_root(3), x);
+		assert_eq!((&cube - &BigInt::one()).nth_root(3), &x - &BigInt::one());
+		assert_eq!((&cube + &BigInt::one()).nth_root(3), x);
+	}
+
+	#[test]
+	fn gcd() {
+		assert_eq!(big(12).gcd(&big(18)), big(6));
+		assert_eq!(big(17).gcd(&big(3120)), big(1));
+		assert_eq!(big(0).gcd(&big(5)), big(5));
+	}
+
+	#[test]
+	fn hex_round_trip() {
+		for &hex in ["0", "1", "f", "deadbeef", "123456789abcdef0123456789abcdef"].iter() {
+			assert_eq!(BigInt::from_hex(hex).to_hex(), hex);
+		}
+		assert_eq!(BigInt::from_hex("000001").to_hex(), "1");
+		assert_eq!(BigInt::from_hex("dead beef\n 0123").to_hex(), "deadbeef0123");
+		assert_eq!(BigInt::from_hex("DEADBEEF"), BigInt::from_u64(0xdeadbeef));
+		assert!(BigInt::try_from_hex("xyz").is_err());
+	}
+
+	#[test]
+	fn bytes_round_trip() {
+		let n = BigInt::from_hex("1234");
+		assert_eq!(n.to_bytes().to_hex(), "1234");
+		assert_eq!(n.to_bytes_padded(4).to_hex(), "00001234");
+		assert_eq!(BigInt::from_bytes(&n.to_bytes_padded(4)), n);
+		assert!(BigInt::zero().to_bytes().is_empty());
+		assert_eq!(BigInt::zero().to_bytes_padded(2).to_hex(), "0000");
+	}
+
+	#[test]
+	fn random_range_bounds() {
+		let (low, high) = (big(1000), big(1010));
+		let mut seen = [false; 10];
+		for _ in 0..1000 {
+			let r = BigInt::random_range(&low, &high);
+			assert!(low <= r && r < high);
+			seen[(&r - &low).to_dec().parse::<usize>().unwrap()] = true;
+		}
+		assert!(seen.iter().all(|s| *s));
+	}
+}
@@ -0,0 +1,78 @@
+use std::cell::RefCell;
+
+use openssl::error::ErrorStack;
+use openssl::symm::{Cipher, Crypter, Mode};
+
+// A keyed block cipher, the modes in CryptoData are built on top of this
+pub trait BlockCipher {
+	fn block_size(&self) -> usize;
+
+	// encrypt/decrypt a single block in place,
+	// block.len() must be equal to block_size()
+	fn encrypt_block(&self, block: &mut [u8]);
+	fn decrypt_block(&self, block: &mut [u8]);
+}
+
+// Block cipher backed by an OpenSSL ECB context
+pub struct OpensslBlockCipher {
+	block_size: usize,
+	enc: RefCell<Crypter>,
+	dec: RefCell<Crypter>,
+}
+
+impl OpensslBlockCipher {
+	// cipher has to be an ECB mode cipher
+	pub fn new(cipher: Cipher, key: &[u8]) -> Result<OpensslBlockCipher, ErrorStack> {
+		let mut enc = Crypter::new(cipher, Mode::Encrypt, key, None)?;
+		let mut dec = Crypter::new(cipher, Mode::Decrypt, key, None)?;
+		// work on single blocks, no padding
+		enc.pad(false);
+		dec.pad(false);
+
+		Ok(OpensslBlockCipher {
+			block_size: cipher.block_size(),
+			enc: RefCell::new(enc),
+			dec: RefCell::new(dec),
+		})
+	}
+
+	pub fn aes_128(key: &[u8]) -> Result<OpensslBlockCipher, ErrorStack> {
+		OpensslBlockCipher::new(Cipher::aes_128_ecb(), key)
+	}
+
+	pub fn aes_192(key: &[u8]) -> Result<OpensslBlockCipher, ErrorStack> {
+		OpensslBlockCipher::new(Cipher::aes_192_ecb(), key)
+	}
+
+	pub fn aes_256(key: &[u8]) -> Result<OpensslBlockCipher, ErrorStack> {
+		OpensslBlockCipher::new(Cipher::aes_256_ecb(), key)
+	}
+
+	// needs the legacy provider with OpenSSL 3
+	pub fn des(key: &[u8]) -> Result<OpensslBlockCipher, ErrorStack> {
+		OpensslBlockCipher::new(Cipher::des_ecb(), key)
+	}
+
+	fn process(&self, crypter: &RefCell<Crypter>, block: &mut [u8]) {
+		assert!(block.len() == self.block_size);
+		// openssl wants an extra block of space in the output buffer
+		let mut out = vec![0u8; 2 * self.block_size];
+		let count = crypter.borrow_mut().update(block, &mut out).unwrap();
+		assert!(count == self.block_size);
+		block.copy_from_slice(&out[..count]);
+	}
+}
+
+impl BlockCipher for OpensslBlockCipher {
+	fn block_size(&self) -> usize {
+		self.block_size
+	}
+
+	fn encrypt_block(&self, block: &mut [u8]) {
+		self.process(&self.enc, block);
+	}
+
+	fn decrypt_block(&self, block: &mut [u8]) {
+		self.process(&self.dec, block);
+	}
+}
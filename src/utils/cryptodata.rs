@@ -1,26 +1,228 @@
 use rand::Rng;
-use byteorder::{LittleEndian, WriteBytesExt};
 
-use std::char;
+use std::error;
 use std::fmt;
+use std::iter::FromIterator;
+use std::ops::{Add, BitXor, Deref, Index};
+use std::slice::{self, SliceIndex};
+use std::string;
 use std::vec;
 
+use openssl::error::ErrorStack;
 use openssl::symm;
-use openssl::symm::{encrypt, Crypter, Mode, Cipher};
+use openssl::symm::{encrypt, Cipher};
+
+use utils::aes::Aes;
+use utils::blockcipher::BlockCipher;
+use utils::ct;
+use utils::ctr::{CounterBlock, CounterLayout, CtrKeystream};
+use utils::gcm;
+use utils::hash::Hash;
+use utils::hmac;
+use utils::md4::Md4;
+use utils::padding::{Padding, Pkcs7};
+use utils::sha1::Sha1;
 
 use base64::Engine;
 
-#[derive (Hash,PartialEq,Eq)]
+#[derive (Debug)]
+pub enum CryptoError {
+	Hex(hex::FromHexError),
+	Base64(base64::DecodeError),
+	Utf8(string::FromUtf8Error),
+	Cipher(ErrorStack),
+	// data length isn't a multiple of the block size
+	BlockLength(usize, usize),
+	KeyLength(usize),
+	IvLength(usize),
+	NonceLength(usize),
+	// initial counter doesn't fit in the counter field
+	CounterOverflow,
+	TagLength(usize),
+	DigestLength(usize),
+	// authentication tag doesn't match
+	Authentication,
+	Padding,
+	EmptyData,
+	// public key outside of the group or a small subgroup
+	PublicKey,
+}
+
+impl fmt::Display for CryptoError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			CryptoError::Hex(ref e) => write!(f, "invalid hex: {}", e),
+			CryptoError::Base64(ref e) => write!(f, "invalid base64: {}", e),
+			CryptoError::Utf8(ref e) => write!(f, "invalid utf-8: {}", e),
+			CryptoError::Cipher(ref e) => write!(f, "cipher error: {}", e),
+			CryptoError::BlockLength(len, bsize) =>
+				write!(f, "data length {} is not a multiple of block size {}", len, bsize),
+			CryptoError::KeyLength(len) => write!(f, "invalid key length {}", len),
+			CryptoError::IvLength(len) => write!(f, "invalid IV length {}", len),
+			CryptoError::NonceLength(len) => write!(f, "invalid nonce length {}", len),
+			CryptoError::CounterOverflow => write!(f, "counter doesn't fit in the counter block"),
+			CryptoError::TagLength(len) => write!(f, "invalid tag length {}", len),
+			CryptoError::DigestLength(len) => write!(f, "invalid digest length {}", len),
+			CryptoError::Authentication => write!(f, "authentication failed"),
+			CryptoError::Padding => write!(f, "invalid padding"),
+			CryptoError::EmptyData => write!(f, "empty data"),
+			CryptoError::PublicKey => write!(f, "invalid public key"),
+		}
+	}
+}
+
+impl error::Error for CryptoError {}
+
+impl From<hex::FromHexError> for CryptoError {
+	fn from(e: hex::FromHexError) -> CryptoError {
+		CryptoError::Hex(e)
+	}
+}
+
+impl From<base64::DecodeError> for CryptoError {
+	fn from(e: base64::DecodeError) -> CryptoError {
+		CryptoError::Base64(e)
+	}
+}
+
+impl From<string::FromUtf8Error> for CryptoError {
+	fn from(e: string::FromUtf8Error) -> CryptoError {
+		CryptoError::Utf8(e)
+	}
+}
+
+impl From<ErrorStack> for CryptoError {
+	fn from(e: ErrorStack) -> CryptoError {
+		CryptoError::Cipher(e)
+	}
+}
+
+// CFB segment size
+#[derive (Clone,Copy,Debug,PartialEq,Eq)]
+pub enum CfbSegment {
+	// CFB-8, one byte per cipher call
+	Bits8,
+	// CFB-128 for AES
+	Block,
+}
+
+#[derive (Clone,Hash,PartialEq,Eq,PartialOrd,Ord)]
 pub struct CryptoData {
 	data: Vec<u8>,
 }
 
+// xor in place with a repeating key, a key at least as long as data
+// is just xored byte by byte
+pub fn xor_slice(data: &mut [u8], key: &[u8]) {
+	for (d, k) in data.iter_mut().zip(key.iter().cycle()) {
+		*d ^= *k;
+	}
+}
+
 impl fmt::Display for CryptoData {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		write!(f, "{}", self.to_hex())
 	}
 }
 
+impl fmt::Debug for CryptoData {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "CryptoData({})", self.to_hex())
+	}
+}
+
+// read-only access to the underlying bytes without copying
+impl Deref for CryptoData {
+	type Target = [u8];
+
+	fn deref(&self) -> &[u8] {
+		&self.data
+	}
+}
+
+impl AsRef<[u8]> for CryptoData {
+	fn as_ref(&self) -> &[u8] {
+		&self.data
+	}
+}
+
+impl<I: SliceIndex<[u8]>> Index<I> for CryptoData {
+	type Output = I::Output;
+
+	fn index(&self, idx: I) -> &I::Output {
+		&self.data[idx]
+	}
+}
+
+impl<'a> From<&'a [u8]> for CryptoData {
+	fn from(bytes: &'a [u8]) -> CryptoData {
+		CryptoData::from_slice(bytes)
+	}
+}
+
+impl<'a> From<&'a str> for CryptoData {
+	fn from(text: &'a str) -> CryptoData {
+		CryptoData::from_text(text)
+	}
+}
+
+impl From<Vec<u8>> for CryptoData {
+	fn from(vec: Vec<u8>) -> CryptoData {
+		CryptoData::from_owned(vec)
+	}
+}
+
+impl FromIterator<u8> for CryptoData {
+	fn from_iter<T: IntoIterator<Item = u8>>(iter: T) -> CryptoData {
+		CryptoData { data: iter.into_iter().collect() }
+	}
+}
+
+impl<'a> IntoIterator for &'a CryptoData {
+	type Item = &'a u8;
+	type IntoIter = slice::Iter<'a, u8>;
+
+	fn into_iter(self) -> slice::Iter<'a, u8> {
+		self.data.iter()
+	}
+}
+
+// a ^ b, the key is repeated as with xor()
+impl<'a, 'b> BitXor<&'b CryptoData> for &'a CryptoData {
+	type Output = CryptoData;
+
+	fn bitxor(self, key: &'b CryptoData) -> CryptoData {
+		self.xor(key)
+	}
+}
+
+impl<'b> BitXor<&'b CryptoData> for CryptoData {
+	type Output = CryptoData;
+
+	fn bitxor(mut self, key: &'b CryptoData) -> CryptoData {
+		self.xor_assign(key);
+		self
+	}
+}
+
+// a + b concatenates
+impl<'a, 'b> Add<&'b CryptoData> for &'a CryptoData {
+	type Output = CryptoData;
+
+	fn add(self, other: &'b CryptoData) -> CryptoData {
+		self.cat(other)
+	}
+}
+
+impl<'b> Add<&'b CryptoData> for CryptoData {
+	type Output = CryptoData;
+
+	fn add(mut self, other: &'b CryptoData) -> CryptoData {
+		self.extend(other);
+		self
+	}
+}
+
 impl CryptoData {
 	pub fn new() -> CryptoData {
 		CryptoData { data: Vec::new() }
@@ -30,15 +232,10 @@ impl CryptoData {
 		let mut rng = rand::rng();
 		//let n: u32 = rng.gen_range(0..100);
 	
-		//TODO: seems to be impossible to use array and fill_bytes,
-		// because the size isn't known at compile time
-		let mut bytes = Vec::new();
-		for _ in 0..size {
-			let x: u8 = rng.random();
-			bytes.push(x);
-		}
+		let mut bytes = vec![0u8; size];
+		rng.fill(bytes.as_mut_slice());
 
-		CryptoData::from_vec(&bytes)
+		CryptoData { data: bytes }
 	}
 
 	pub fn zero(size: usize) -> CryptoData {
@@ -51,43 +248,51 @@ impl CryptoData {
 		self.slice(0, count)
 	}
 
-	pub fn clone(&self) -> CryptoData {
-		CryptoData { data: self.data.clone() }
+	pub fn from_hex(hexstring: &str) -> CryptoData {
+		CryptoData::try_from_hex(hexstring).unwrap()
 	}
 
-	pub fn from_hex(hexstring: &str) -> CryptoData {
-		let vec = hex::decode(hexstring).unwrap();
-		CryptoData { data: vec }
-//		CryptoData { data: hexstring.from_hex().unwrap() }
+	pub fn try_from_hex(hexstring: &str) -> Result<CryptoData, CryptoError> {
+		let vec = hex::decode(hexstring)?;
+		Ok(CryptoData { data: vec })
 	}
 
 	pub fn from_text(ascii: &str) -> CryptoData {
-		let bytes: Vec<u8> = ascii.as_bytes().into_iter().map(|b| *b).collect();
-		CryptoData { data: bytes }
+		CryptoData { data: ascii.as_bytes().to_vec() }
 	}
 
 	pub fn from_vec(vec: &Vec<u8>) -> CryptoData {
 		CryptoData { data: vec.clone() }
 	}
 
+	// take ownership of the vector, no copy is made
+	pub fn from_owned(vec: Vec<u8>) -> CryptoData {
+		CryptoData { data: vec }
+	}
+
+	pub fn from_slice(bytes: &[u8]) -> CryptoData {
+		CryptoData { data: bytes.to_vec() }
+	}
+
 	pub fn from_byte(byte: u8) -> CryptoData {
 		let vec = vec![byte];
 		CryptoData { data: vec }
 	}
 
 	pub fn from_base64(base64_str: &str) -> CryptoData {
-		// TODO: handle errors
+		CryptoData::try_from_base64(base64_str).expect("Failed to decode base64 data.")
+	}
+
+	pub fn try_from_base64(base64_str: &str) -> Result<CryptoData, CryptoError> {
 		// base64 can't handle newlines
-		let nowhitespace = String::from_utf8(base64_str.bytes()
+		let nowhitespace: Vec<u8> = base64_str.bytes()
 							.filter(|b| !b" \n\t\r\x0b\x0c"
-							.contains(b)).collect())
-							.unwrap();
-	
+							.contains(b)).collect();
+
 		let byte_str = base64::prelude::BASE64_STANDARD
-					.decode(nowhitespace)
-					.expect("Failed to decode base64 data.");
+					.decode(nowhitespace)?;
 
-		CryptoData { data: byte_str.clone() }
+		Ok(CryptoData { data: byte_str })
 	}
 
 	pub fn to_base64(&self) -> String {
@@ -99,21 +304,39 @@ impl CryptoData {
 	}
 
 	pub fn to_text(&self) -> String {
-		String::from_utf8(self.data.clone()).unwrap()
+		self.try_to_text().unwrap()
+	}
+
+	pub fn try_to_text(&self) -> Result<String, CryptoError> {
+		Ok(String::from_utf8(self.data.clone())?)
 	}
 
 	pub fn vec(&self) -> &Vec<u8> {
 		&self.data
 	}
 
+	pub fn into_vec(self) -> Vec<u8> {
+		self.data
+	}
+
 	pub fn block(&self, idx: usize, bsize: usize) -> CryptoData {
 		self.slice(idx * bsize, (idx + 1) * bsize)
 	}
 
+	// borrowed version of block()
+	pub fn block_view(&self, idx: usize, bsize: usize) -> &[u8] {
+		self.view(idx * bsize, (idx + 1) * bsize)
+	}
+
 	pub fn slice(&self, start: usize, end: usize) -> CryptoData {
+		CryptoData::from_slice(self.view(start, end))
+	}
+
+	// borrowed version of slice()
+	pub fn view(&self, start: usize, end: usize) -> &[u8] {
 		assert!(start <= end);
 		assert!(end <= self.data.len());
-		CryptoData { data: self.data[start..end].to_vec() }
+		&self.data[start..end]
 	}
 
 	pub fn len(&self) -> usize {
@@ -121,48 +344,49 @@ impl CryptoData {
 	}
 
 	pub fn cat(&self, other: &CryptoData) -> CryptoData {
-		let mut res = self.data.clone();
-		//res.push(&self.data);
-		res.append(&mut other.vec().clone());
+		let mut res = Vec::with_capacity(self.len() + other.len());
+		res.extend_from_slice(&self.data);
+		res.extend_from_slice(&other.data);
 
 		CryptoData { data: res }
 	}
 
+	// append bytes in place
+	pub fn extend(&mut self, other: &[u8]) {
+		self.data.extend_from_slice(other);
+	}
+
 	pub fn xor(&self, key: &CryptoData) -> CryptoData {
-		let mut res: Vec<u8> = Vec::new();
-		let mut data_it = self.data.iter();
-
-		// TODO: wtf goto?
-		'outer: loop {
-			for k in key.vec().iter() {
-				let d =
-				match data_it.next() {
-					None => break 'outer,
-					Some(d) => *d,
-				};
-
-				let xor = *k ^ d;
-				res.push(xor);
-			}
-		}
+		let mut res = self.clone();
+		res.xor_assign(key);
+		res
+	}
 
-		CryptoData { data: res, }
+	// xor in place with a repeating key
+	pub fn xor_assign(&mut self, key: &[u8]) {
+		xor_slice(&mut self.data, key);
 	}
 
+	// PKCS#7
 	pub fn pad(&self, bsize: usize) -> CryptoData {
-		let mut res = self.data.clone();
-		let pad_size = bsize - res.len() % bsize;
-		//println!("len: {}, pad: {}", res.len(), pad_size);
-		let pad_byte = char::from_u32(pad_size as u32).unwrap();
-		for _ in 0..pad_size {
-			res.push(pad_byte as u8);
-		}
+		self.pad_with(&Pkcs7, bsize)
+	}
+
+	pub fn pad_with<P: Padding>(&self, padding: &P, bsize: usize) -> CryptoData {
+		let mut res = Vec::with_capacity(self.len() + bsize);
+		res.extend_from_slice(&self.data);
+		padding.pad(&mut res, bsize);
 		CryptoData { data: res }
 	}
 
+	// leaks the position of the first bad byte through timing,
+	// see pad_verify_ct() for the hardened version
 	pub fn pad_verify(&self, bsize: usize) -> bool {
 		let len = self.data.len();
-		let pad_byte = *self.data.last().unwrap();
+		let pad_byte = match self.data.last() {
+			Some(b) => *b,
+			None => return false,
+		};
 		let pad_size = pad_byte as usize;
 
 		// is it padded to bsize?
@@ -185,30 +409,23 @@ impl CryptoData {
 		true
 	}
 
-	pub fn pad_strip(&self, bsize: usize) -> CryptoData {
-		let len = self.data.len();
-		let pad_byte = *self.data.last().unwrap();
-		let pad_size = pad_byte as usize;
+	// constant-time PKCS#7 check
+	pub fn pad_verify_ct(&self, bsize: usize) -> bool {
+		ct::ct_pkcs7_verify(&self.data, bsize)
+	}
 
-		// is it padded to bsize?
-		if len % bsize != 0 {
-			return self.clone();
-		}
+	// returns the data unchanged if the padding is invalid
+	pub fn pad_strip(&self, bsize: usize) -> CryptoData {
+		self.try_pad_strip(bsize).unwrap_or_else(|_| self.clone())
+	}
 
-		let mut ok = true;
-		// check that all padding bytes are the same
-		for i in 1..pad_size {
-			if self.data[len - i] != pad_byte {
-				ok = false;
-			}
-		}
+	pub fn try_pad_strip(&self, bsize: usize) -> Result<CryptoData, CryptoError> {
+		self.pad_strip_with(&Pkcs7, bsize)
+	}
 
-		if ok {
-			let new_slice = &self.vec()[0..len - 1];
-			CryptoData::from_vec(&new_slice.to_vec())
-		} else {
-			self.clone()
-		}
+	pub fn pad_strip_with<P: Padding>(&self, padding: &P, bsize: usize) -> Result<CryptoData, CryptoError> {
+		let len = padding.unpadded_len(&self.data, bsize)?;
+		Ok(self.slice(0, len))
 	}
 
 	pub fn encrypt(&self, key: &CryptoData, iv: &CryptoData, cipher: Cipher) -> CryptoData {
@@ -224,123 +441,188 @@ impl CryptoData {
 	}
 
 	pub fn decrypt(&self, key: &CryptoData, iv: &CryptoData, cipher: Cipher) -> CryptoData {
+		self.try_decrypt(key, iv, cipher).unwrap()
+	}
+
+	pub fn try_decrypt(&self, key: &CryptoData, iv: &CryptoData, cipher: Cipher) -> Result<CryptoData, CryptoError> {
 		//println!("data: {}, key {}", self.to_hex(), key.to_hex());
 		let decrypted = symm::decrypt(cipher,
 						key.vec(),
 						Some(iv.vec()),
-						&self.data).unwrap();
+						&self.data)?;
 
 		//println!("res: {}", decrypted.to_hex());
-		CryptoData { data: decrypted }
+		Ok(CryptoData { data: decrypted })
 	}
 
+	// The AES variant in ECB/CBC/CTR is picked from the key length
+	// (16, 24 or 32 bytes), use Aes::with_variant() and the *_with
+	// methods to require a specific one.
 	pub fn ECB_encrypt(&self, key: &CryptoData) -> CryptoData {
-		//self.encrypt(key, &CryptoData::new(), symm::AES_128_ECB)
+		self.try_ECB_encrypt(key).unwrap()
+	}
+
+	pub fn try_ECB_encrypt(&self, key: &CryptoData) -> Result<CryptoData, CryptoError> {
+		let cipher = Aes::new(key)?;
+		Ok(self.ECB_encrypt_with(&cipher))
+	}
+
+	pub fn ECB_encrypt_with<C: BlockCipher>(&self, cipher: &C) -> CryptoData {
+		let bsize = cipher.block_size();
 
 		// pad input data if its length doesn't match blocksize
-		let plain = if self.len() % 16 != 0 {
-			self.pad(16)
+		let mut result = if self.len() % bsize != 0 {
+			self.pad(bsize)
 		} else {
 			self.clone()
 		};
 
-		let mut c = Crypter::new(Cipher::aes_128_ecb(), Mode::Encrypt, &key.vec(), Some(&vec![])).unwrap();
-		c.pad(false);
-		let mut r: Vec<u8> = vec![]; 
-		let _ = c.update(plain.vec(), &mut r);
-		let mut rest: Vec<u8> = vec![]; 
-		let _ = c.finalize(&mut rest);
-		r.extend(rest.into_iter());
-		CryptoData { data: r }
+		for block in result.data.chunks_mut(bsize) {
+			cipher.encrypt_block(block);
+		}
+		result
 	}
 
 	pub fn ECB_decrypt(&self, key: &CryptoData) -> CryptoData {
-		//TODO: this doesn't work
-		//https://github.com/sfackler/rust-openssl/issues/40
-		//self.decrypt(key, &CryptoData::new(), symm::AES_128_ECB)
-
-		let mut c = symm::Crypter::new(Cipher::aes_128_ecb(), Mode::Decrypt, key.vec(), Some(&vec![])).unwrap();
-		// need to disable padding, otherwise there's an additional padding block at the end
-		c.pad(false);
-		let block_size = Cipher::aes_128_ecb().block_size();
-		let mut r: Vec<u8> = vec![0; self.data.len() + block_size];
-		let _ = c.update(self.vec(), &mut r);
-		let mut rest: Vec<u8> = vec![0; block_size]; 
-		let _ = c.finalize(&mut rest);
-		r.extend(rest.into_iter());
-		CryptoData { data: r }
+		self.try_ECB_decrypt(key).unwrap()
+	}
+
+	pub fn try_ECB_decrypt(&self, key: &CryptoData) -> Result<CryptoData, CryptoError> {
+		let cipher = Aes::new(key)?;
+		self.try_ECB_decrypt_with(&cipher)
+	}
+
+	pub fn ECB_decrypt_with<C: BlockCipher>(&self, cipher: &C) -> CryptoData {
+		self.try_ECB_decrypt_with(cipher).unwrap()
+	}
+
+	pub fn ECB_encrypt_padded<C: BlockCipher, P: Padding>(&self, cipher: &C, padding: &P) -> CryptoData {
+		self.pad_with(padding, cipher.block_size()).ECB_encrypt_with(cipher)
+	}
+
+	// decrypt and strip the padding
+	pub fn ECB_decrypt_padded<C: BlockCipher, P: Padding>(&self, cipher: &C, padding: &P) -> Result<CryptoData, CryptoError> {
+		self.try_ECB_decrypt_with(cipher)?.pad_strip_with(padding, cipher.block_size())
+	}
+
+	pub fn try_ECB_decrypt_with<C: BlockCipher>(&self, cipher: &C) -> Result<CryptoData, CryptoError> {
+		let bsize = cipher.block_size();
+		if self.len() % bsize != 0 {
+			return Err(CryptoError::BlockLength(self.len(), bsize));
+		}
+
+		let mut result = self.clone();
+		for block in result.data.chunks_mut(bsize) {
+			cipher.decrypt_block(block);
+		}
+		Ok(result)
 	}
 
 	pub fn CBC_encrypt(&self, key: &CryptoData, iv: &CryptoData) -> CryptoData {
-		let mut result = CryptoData::new();
-		let mut to_xor = iv.clone();
+		self.try_CBC_encrypt(key, iv).unwrap()
+	}
+
+	pub fn try_CBC_encrypt(&self, key: &CryptoData, iv: &CryptoData) -> Result<CryptoData, CryptoError> {
+		let cipher = Aes::new(key)?;
+		self.try_CBC_encrypt_with(&cipher, iv)
+	}
+
+	pub fn CBC_encrypt_with<C: BlockCipher>(&self, cipher: &C, iv: &CryptoData) -> CryptoData {
+		self.try_CBC_encrypt_with(cipher, iv).unwrap()
+	}
+
+	pub fn try_CBC_encrypt_with<C: BlockCipher>(&self, cipher: &C, iv: &CryptoData) -> Result<CryptoData, CryptoError> {
+		self.CBC_encrypt_padded(cipher, iv, &Pkcs7)
+	}
+
+	pub fn CBC_encrypt_padded<C: BlockCipher, P: Padding>(&self, cipher: &C, iv: &CryptoData, padding: &P) -> Result<CryptoData, CryptoError> {
+		let bsize = cipher.block_size();
+		if iv.len() != bsize {
+			return Err(CryptoError::IvLength(iv.len()));
+		}
 
 		// pad input data
-		let plain = self.pad(16);
-		let plain_slice = plain.vec().as_slice();
-
-		for idx in (0..plain.len()).step_by(16) {
-			let block_slice = &plain_slice[idx..idx + 16];
-			let block = CryptoData::from_vec(&block_slice.to_vec());
-			let xored = block.xor(&to_xor);
-			let encrypted = xored.ECB_encrypt(key);
-			//println!("enc size: {}", encrypted.len());
-			//println!("enc : {}", encrypted.to_hex());
-			result = result.cat(&encrypted);
-			to_xor = encrypted;
+		let mut result = self.pad_with(padding, bsize);
+		// previous ciphertext block
+		let mut prev = iv.clone();
+
+		for block in result.data.chunks_mut(bsize) {
+			xor_slice(block, &prev);
+			cipher.encrypt_block(block);
+			//println!("enc : {}", CryptoData::from_slice(block));
+			prev.data.copy_from_slice(block);
 		}
-		result
+		Ok(result)
 	}
 
-	// TODO: strip pad
+	// the padding is kept, see CBC_decrypt_padded()
 	pub fn CBC_decrypt(&self, key: &CryptoData, iv: &CryptoData) -> CryptoData {
-		let mut result = CryptoData::new();
-		let mut to_xor = iv.clone();
-		let self_slice = self.data.as_slice();
-
-		for idx in (0..self.len()).step_by(16) {
-			let block_slice = &self_slice[idx..idx + 16];
-			let block = CryptoData::from_vec(&block_slice.to_vec());
-			let decrypted = block.ECB_decrypt(key);
-			let xored = decrypted.xor(&to_xor);
-
-			// xor with ciphertext block
-			to_xor = block;
-			result = result.cat(&xored);
-		}
-		//TODO: doesn't work
-		//result.pad_strip(16)
-		result
+		self.try_CBC_decrypt(key, iv).unwrap()
 	}
 
-	pub fn CTR_encrypt(&self, key: &CryptoData, nonce: &CryptoData, counter: u64) -> CryptoData {
-		let mut result = CryptoData::new();
-		let mut le_ctr = counter;
+	pub fn try_CBC_decrypt(&self, key: &CryptoData, iv: &CryptoData) -> Result<CryptoData, CryptoError> {
+		let cipher = Aes::new(key)?;
+		self.try_CBC_decrypt_with(&cipher, iv)
+	}
+
+	pub fn CBC_decrypt_with<C: BlockCipher>(&self, cipher: &C, iv: &CryptoData) -> CryptoData {
+		self.try_CBC_decrypt_with(cipher, iv).unwrap()
+	}
 
-		let mut le = vec![];
-		le.write_u64::<LittleEndian>(counter).unwrap();
+	pub fn try_CBC_decrypt_with<C: BlockCipher>(&self, cipher: &C, iv: &CryptoData) -> Result<CryptoData, CryptoError> {
+		let bsize = cipher.block_size();
+		if iv.len() != bsize {
+			return Err(CryptoError::IvLength(iv.len()));
+		}
+		if self.len() % bsize != 0 {
+			return Err(CryptoError::BlockLength(self.len(), bsize));
+		}
 
-		let ctr_cd = CryptoData::from_vec(&le);
-		let mut nonce_ctr = nonce.cat(&ctr_cd);
+		let mut result = self.clone();
+		let mut to_xor: &[u8] = iv;
 
-		for idx in (0..self.data.len()).step_by(16) {
-			let end = if idx + 16 < self.data.len() {
-				idx + 16
-			} else {
-				self.data.len()
-			};
+		for (block, enc) in result.data.chunks_mut(bsize).zip(self.chunks(bsize)) {
+			cipher.decrypt_block(block);
+
+			// xor with previous ciphertext block
+			xor_slice(block, to_xor);
+			to_xor = enc;
+		}
+		Ok(result)
+	}
+
+	// decrypt and strip the padding
+	pub fn CBC_decrypt_padded<C: BlockCipher, P: Padding>(&self, cipher: &C, iv: &CryptoData, padding: &P) -> Result<CryptoData, CryptoError> {
+		self.try_CBC_decrypt_with(cipher, iv)?.pad_strip_with(padding, cipher.block_size())
+	}
+
+	pub fn CTR_encrypt(&self, key: &CryptoData, nonce: &CryptoData, counter: u64) -> CryptoData {
+		self.try_CTR_encrypt(key, nonce, counter).unwrap()
+	}
 
-			let block_slice = &self.data[idx..end];
-			let block = CryptoData::from_vec(&block_slice.to_vec());
-			let encrypted = nonce_ctr.ECB_encrypt(key);
-			let xored = block.xor(&encrypted.cut(block.len()));
-			result = result.cat(&xored);
+	pub fn try_CTR_encrypt(&self, key: &CryptoData, nonce: &CryptoData, counter: u64) -> Result<CryptoData, CryptoError> {
+		let cipher = Aes::new(key)?;
+		let ctr = CounterBlock::from_nonce(nonce, counter, CounterLayout::LittleEndian64, cipher.block_size())?;
+		Ok(self.CTR_encrypt_counter(&cipher, ctr))
+	}
+
+	// nonce followed by a little-endian 64 bit counter
+	pub fn CTR_encrypt_with<C: BlockCipher>(&self, cipher: &C, nonce: &CryptoData, counter: u64) -> CryptoData {
+		let ctr = CounterBlock::from_nonce(nonce, counter, CounterLayout::LittleEndian64, cipher.block_size()).unwrap();
+		self.CTR_encrypt_counter(cipher, ctr)
+	}
 
-			le_ctr += 1;
-			le.write_u64::<LittleEndian>(le_ctr).unwrap();
-			le.write_u64::<LittleEndian>(counter).unwrap();
-			let new_ctr = CryptoData::from_vec(&le);
-			nonce_ctr = nonce.cat(&new_ctr);
+	// CTR with an arbitrary counter block layout
+	pub fn CTR_encrypt_counter<C: BlockCipher>(&self, cipher: &C, mut ctr: CounterBlock) -> CryptoData {
+		assert!(ctr.block().len() == cipher.block_size());
+		let mut result = self.clone();
+		let mut keystream = vec![0u8; cipher.block_size()];
+
+		for block in result.data.chunks_mut(cipher.block_size()) {
+			keystream.copy_from_slice(ctr.block());
+			cipher.encrypt_block(&mut keystream);
+			xor_slice(block, &keystream);
+			ctr.increment();
 		}
 		result
 	}
@@ -350,6 +632,289 @@ impl CryptoData {
 		self.CTR_encrypt(key, nonce, counter)
 	}
 
+	pub fn try_CTR_decrypt(&self, key: &CryptoData, nonce: &CryptoData, counter: u64) -> Result<CryptoData, CryptoError> {
+		self.try_CTR_encrypt(key, nonce, counter)
+	}
+
+	pub fn CTR_decrypt_with<C: BlockCipher>(&self, cipher: &C, nonce: &CryptoData, counter: u64) -> CryptoData {
+		self.CTR_encrypt_with(cipher, nonce, counter)
+	}
+
+	pub fn CTR_decrypt_counter<C: BlockCipher>(&self, cipher: &C, ctr: CounterBlock) -> CryptoData {
+		self.CTR_encrypt_counter(cipher, ctr)
+	}
+
+	pub fn CFB_encrypt(&self, key: &CryptoData, iv: &CryptoData, segment: CfbSegment) -> CryptoData {
+		self.try_CFB_encrypt(key, iv, segment).unwrap()
+	}
+
+	pub fn try_CFB_encrypt(&self, key: &CryptoData, iv: &CryptoData, segment: CfbSegment) -> Result<CryptoData, CryptoError> {
+		let cipher = Aes::new(key)?;
+		self.try_CFB_encrypt_with(&cipher, iv, segment)
+	}
+
+	pub fn CFB_decrypt(&self, key: &CryptoData, iv: &CryptoData, segment: CfbSegment) -> CryptoData {
+		self.try_CFB_decrypt(key, iv, segment).unwrap()
+	}
+
+	pub fn try_CFB_decrypt(&self, key: &CryptoData, iv: &CryptoData, segment: CfbSegment) -> Result<CryptoData, CryptoError> {
+		let cipher = Aes::new(key)?;
+		self.try_CFB_decrypt_with(&cipher, iv, segment)
+	}
+
+	pub fn CFB_encrypt_with<C: BlockCipher>(&self, cipher: &C, iv: &CryptoData, segment: CfbSegment) -> CryptoData {
+		self.try_CFB_encrypt_with(cipher, iv, segment).unwrap()
+	}
+
+	pub fn try_CFB_encrypt_with<C: BlockCipher>(&self, cipher: &C, iv: &CryptoData, segment: CfbSegment) -> Result<CryptoData, CryptoError> {
+		self.CFB_process(cipher, iv, segment, true)
+	}
+
+	pub fn CFB_decrypt_with<C: BlockCipher>(&self, cipher: &C, iv: &CryptoData, segment: CfbSegment) -> CryptoData {
+		self.try_CFB_decrypt_with(cipher, iv, segment).unwrap()
+	}
+
+	pub fn try_CFB_decrypt_with<C: BlockCipher>(&self, cipher: &C, iv: &CryptoData, segment: CfbSegment) -> Result<CryptoData, CryptoError> {
+		self.CFB_process(cipher, iv, segment, false)
+	}
+
+	// no padding, the last segment can be shorter
+	fn CFB_process<C: BlockCipher>(&self, cipher: &C, iv: &CryptoData, segment: CfbSegment, encrypt: bool) -> Result<CryptoData, CryptoError> {
+		let bsize = cipher.block_size();
+		if iv.len() != bsize {
+			return Err(CryptoError::IvLength(iv.len()));
+		}
+
+		let seg_size = match segment {
+			CfbSegment::Bits8 => 1,
+			CfbSegment::Block => bsize,
+		};
+		let mut result = self.clone();
+		// shift register, filled with previous ciphertext
+		let mut register = iv.clone().into_vec();
+		let mut keystream = vec![0u8; bsize];
+
+		for seg in result.data.chunks_mut(seg_size) {
+			keystream.copy_from_slice(&register);
+			cipher.encrypt_block(&mut keystream);
+
+			let enc = if encrypt {
+				xor_slice(seg, &keystream);
+				seg.to_vec()
+			} else {
+				let enc = seg.to_vec();
+				xor_slice(seg, &keystream);
+				enc
+			};
+
+			register.drain(..enc.len());
+			register.extend_from_slice(&enc);
+		}
+		Ok(result)
+	}
+
+	pub fn OFB_encrypt(&self, key: &CryptoData, iv: &CryptoData) -> CryptoData {
+		self.try_OFB_encrypt(key, iv).unwrap()
+	}
+
+	pub fn try_OFB_encrypt(&self, key: &CryptoData, iv: &CryptoData) -> Result<CryptoData, CryptoError> {
+		let cipher = Aes::new(key)?;
+		self.try_OFB_encrypt_with(&cipher, iv)
+	}
+
+	// same as encryption
+	pub fn OFB_decrypt(&self, key: &CryptoData, iv: &CryptoData) -> CryptoData {
+		self.OFB_encrypt(key, iv)
+	}
+
+	pub fn try_OFB_decrypt(&self, key: &CryptoData, iv: &CryptoData) -> Result<CryptoData, CryptoError> {
+		self.try_OFB_encrypt(key, iv)
+	}
+
+	pub fn OFB_encrypt_with<C: BlockCipher>(&self, cipher: &C, iv: &CryptoData) -> CryptoData {
+		self.try_OFB_encrypt_with(cipher, iv).unwrap()
+	}
+
+	pub fn try_OFB_encrypt_with<C: BlockCipher>(&self, cipher: &C, iv: &CryptoData) -> Result<CryptoData, CryptoError> {
+		let bsize = cipher.block_size();
+		if iv.len() != bsize {
+			return Err(CryptoError::IvLength(iv.len()));
+		}
+
+		let mut result = self.clone();
+		let mut keystream = iv.clone().into_vec();
+		for block in result.data.chunks_mut(bsize) {
+			cipher.encrypt_block(&mut keystream);
+			xor_slice(block, &keystream);
+		}
+		Ok(result)
+	}
+
+	pub fn OFB_decrypt_with<C: BlockCipher>(&self, cipher: &C, iv: &CryptoData) -> CryptoData {
+		self.OFB_encrypt_with(cipher, iv)
+	}
+
+	pub fn try_OFB_decrypt_with<C: BlockCipher>(&self, cipher: &C, iv: &CryptoData) -> Result<CryptoData, CryptoError> {
+		self.try_OFB_encrypt_with(cipher, iv)
+	}
+
+	pub fn PCBC_encrypt(&self, key: &CryptoData, iv: &CryptoData) -> CryptoData {
+		self.try_PCBC_encrypt(key, iv).unwrap()
+	}
+
+	pub fn try_PCBC_encrypt(&self, key: &CryptoData, iv: &CryptoData) -> Result<CryptoData, CryptoError> {
+		let cipher = Aes::new(key)?;
+		self.try_PCBC_encrypt_with(&cipher, iv)
+	}
+
+	// the padding is kept, see PCBC_decrypt_padded()
+	pub fn PCBC_decrypt(&self, key: &CryptoData, iv: &CryptoData) -> CryptoData {
+		self.try_PCBC_decrypt(key, iv).unwrap()
+	}
+
+	pub fn try_PCBC_decrypt(&self, key: &CryptoData, iv: &CryptoData) -> Result<CryptoData, CryptoError> {
+		let cipher = Aes::new(key)?;
+		self.try_PCBC_decrypt_with(&cipher, iv)
+	}
+
+	// like CBC, but the next block is xored with both the previous
+	// plaintext and ciphertext block
+	pub fn PCBC_encrypt_with<C: BlockCipher>(&self, cipher: &C, iv: &CryptoData) -> CryptoData {
+		self.try_PCBC_encrypt_with(cipher, iv).unwrap()
+	}
+
+	pub fn try_PCBC_encrypt_with<C: BlockCipher>(&self, cipher: &C, iv: &CryptoData) -> Result<CryptoData, CryptoError> {
+		self.PCBC_encrypt_padded(cipher, iv, &Pkcs7)
+	}
+
+	pub fn PCBC_encrypt_padded<C: BlockCipher, P: Padding>(&self, cipher: &C, iv: &CryptoData, padding: &P) -> Result<CryptoData, CryptoError> {
+		let bsize = cipher.block_size();
+		if iv.len() != bsize {
+			return Err(CryptoError::IvLength(iv.len()));
+		}
+
+		let mut result = self.pad_with(padding, bsize);
+		let mut to_xor = iv.clone().into_vec();
+
+		for block in result.data.chunks_mut(bsize) {
+			let plain = block.to_vec();
+			xor_slice(block, &to_xor);
+			cipher.encrypt_block(block);
+
+			to_xor.copy_from_slice(block);
+			xor_slice(&mut to_xor, &plain);
+		}
+		Ok(result)
+	}
+
+	pub fn PCBC_decrypt_with<C: BlockCipher>(&self, cipher: &C, iv: &CryptoData) -> CryptoData {
+		self.try_PCBC_decrypt_with(cipher, iv).unwrap()
+	}
+
+	pub fn try_PCBC_decrypt_with<C: BlockCipher>(&self, cipher: &C, iv: &CryptoData) -> Result<CryptoData, CryptoError> {
+		let bsize = cipher.block_size();
+		if iv.len() != bsize {
+			return Err(CryptoError::IvLength(iv.len()));
+		}
+		if self.len() % bsize != 0 {
+			return Err(CryptoError::BlockLength(self.len(), bsize));
+		}
+
+		let mut result = self.clone();
+		let mut to_xor = iv.clone().into_vec();
+
+		for block in result.data.chunks_mut(bsize) {
+			let enc = block.to_vec();
+			cipher.decrypt_block(block);
+			xor_slice(block, &to_xor);
+
+			to_xor.copy_from_slice(block);
+			xor_slice(&mut to_xor, &enc);
+		}
+		Ok(result)
+	}
+
+	// decrypt and strip the padding
+	pub fn PCBC_decrypt_padded<C: BlockCipher, P: Padding>(&self, cipher: &C, iv: &CryptoData, padding: &P) -> Result<CryptoData, CryptoError> {
+		self.try_PCBC_decrypt_with(cipher, iv)?.pad_strip_with(padding, cipher.block_size())
+	}
+
+	// AES-GCM, returns the ciphertext and a tag_len bytes long tag
+	pub fn GCM_encrypt(&self, key: &CryptoData, iv: &CryptoData, aad: &CryptoData, tag_len: usize) -> Result<(CryptoData, CryptoData), CryptoError> {
+		let cipher = Aes::new(key)?;
+		self.GCM_encrypt_with(&cipher, iv, aad, tag_len)
+	}
+
+	pub fn GCM_decrypt(&self, key: &CryptoData, iv: &CryptoData, aad: &CryptoData, tag: &CryptoData) -> Result<CryptoData, CryptoError> {
+		let cipher = Aes::new(key)?;
+		self.GCM_decrypt_with(&cipher, iv, aad, tag)
+	}
+
+	pub fn GCM_encrypt_with<C: BlockCipher>(&self, cipher: &C, iv: &CryptoData, aad: &CryptoData, tag_len: usize) -> Result<(CryptoData, CryptoData), CryptoError> {
+		if tag_len < 4 || tag_len > 16 {
+			return Err(CryptoError::TagLength(tag_len));
+		}
+		if iv.len() == 0 {
+			return Err(CryptoError::IvLength(0));
+		}
+
+		let h = gcm::hash_key(cipher);
+		let j0 = gcm::pre_counter(&h, iv);
+		let mut ctr = CounterBlock::from_block(&j0, CounterLayout::BigEndian32)?;
+		ctr.increment();
+
+		let ciphertext = self.CTR_encrypt_counter(cipher, ctr);
+		let tag = CryptoData::GCM_tag(cipher, &h, &j0, aad, &ciphertext, tag_len);
+		Ok((ciphertext, tag))
+	}
+
+	pub fn GCM_decrypt_with<C: BlockCipher>(&self, cipher: &C, iv: &CryptoData, aad: &CryptoData, tag: &CryptoData) -> Result<CryptoData, CryptoError> {
+		if tag.len() < 4 || tag.len() > 16 {
+			return Err(CryptoError::TagLength(tag.len()));
+		}
+		if iv.len() == 0 {
+			return Err(CryptoError::IvLength(0));
+		}
+
+		let h = gcm::hash_key(cipher);
+		let j0 = gcm::pre_counter(&h, iv);
+		let expected = CryptoData::GCM_tag(cipher, &h, &j0, aad, self, tag.len());
+		if !expected.ct_eq(tag) {
+			return Err(CryptoError::Authentication);
+		}
+
+		let mut ctr = CounterBlock::from_block(&j0, CounterLayout::BigEndian32)?;
+		ctr.increment();
+		Ok(self.CTR_decrypt_counter(cipher, ctr))
+	}
+
+	// MSB_t(E(J0) ^ GHASH(A, C))
+	fn GCM_tag<C: BlockCipher>(cipher: &C, h: &[u8], j0: &[u8], aad: &[u8], ciphertext: &[u8], tag_len: usize) -> CryptoData {
+		let mut tag = j0.to_vec();
+		cipher.encrypt_block(&mut tag);
+		xor_slice(&mut tag, &gcm::ghash(h, aad, ciphertext));
+		tag.truncate(tag_len);
+		CryptoData { data: tag }
+	}
+
+	// replace the plaintext at offset with newtext,
+	// only the keystream covering the edited bytes is generated
+	pub fn CTR_edit<C: BlockCipher>(&mut self, keystream: &CtrKeystream<C>, offset: usize, newtext: &[u8]) {
+		assert!(offset <= self.len());
+		let mut enc = newtext.to_vec();
+		keystream.apply(offset, &mut enc);
+
+		let end = self.len().min(offset + enc.len());
+		let overlap = end - offset;
+		self.data[offset..end].copy_from_slice(&enc[..overlap]);
+		self.data.extend_from_slice(&enc[overlap..]);
+	}
+
+	// constant-time comparison, only the length may leak
+	pub fn ct_eq(&self, other: &CryptoData) -> bool {
+		ct::ct_eq(&self.data, &other.data)
+	}
+
 	// count Hamming distance to a data string
 	pub fn hamming_distance(&self, other: &CryptoData) -> usize {
 		let mut total_dist = 0;
@@ -382,22 +947,142 @@ impl CryptoData {
 	}
 
 	pub fn SHA1_mac_prefix(&self, key: &CryptoData) -> CryptoData {
-		use crypto::digest::Digest;
-		use crypto::sha1::Sha1;
-		let mut digest: [u8; 20] = [0; 20];
-	
-		let binding = key.cat(self);
-		let to_mac = binding.vec();
-/*
-		use openssl::sha;
-		let mut hasher = sha::Sha1::new();
-		hasher.update(to_mac);
-		let mut digest = hasher.finish();
-*/
 		let mut sha = Sha1::new();
-		sha.input(&to_mac);
-		sha.result(&mut digest);
+		sha.update(key);
+		sha.update(self);
+		CryptoData::from_slice(&sha.digest())
+	}
+
+	pub fn MD4_mac_prefix(&self, key: &CryptoData) -> CryptoData {
+		let mut md4 = Md4::new();
+		md4.update(key);
+		md4.update(self);
+		CryptoData::from_slice(&md4.digest())
+	}
+
+	pub fn HMAC<H: Hash>(&self, key: &CryptoData) -> CryptoData {
+		CryptoData::from_owned(hmac::hmac::<H>(key, self))
+	}
+
+	pub fn HMAC_verify<H: Hash>(&self, key: &CryptoData, tag: &CryptoData) -> bool {
+		hmac::hmac_verify::<H>(key, self, tag)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use utils::aes::Aes;
+	use utils::cryptodata::{CfbSegment, CryptoData, CryptoError};
+	use utils::padding::Iso10126;
+
+	// NIST SP 800-38A F.3 and F.4, AES-128
+	const KEY: &str = "2b7e151628aed2a6abf7158809cf4f3c";
+	const IV: &str = "000102030405060708090a0b0c0d0e0f";
+	const PLAIN: &str = "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+		30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710";
+
+	fn vectors() -> (CryptoData, CryptoData, CryptoData) {
+		(CryptoData::from_hex(KEY), CryptoData::from_hex(IV), CryptoData::from_hex(PLAIN))
+	}
+
+	#[test]
+	fn sp800_38a_cfb8() {
+		let (key, iv, plain) = vectors();
+		let plain = plain.cut(18);
+		let enc = plain.CFB_encrypt(&key, &iv, CfbSegment::Bits8);
+		assert_eq!(enc.to_hex(), "3b79424c9c0dd436bace9e0ed4586a4f32b9");
+		assert_eq!(enc.CFB_decrypt(&key, &iv, CfbSegment::Bits8), plain);
+	}
+
+	#[test]
+	fn sp800_38a_cfb128() {
+		let (key, iv, plain) = vectors();
+		let enc = plain.CFB_encrypt(&key, &iv, CfbSegment::Block);
+		assert_eq!(enc.to_hex(), "3b3fd92eb72dad20333449f8e83cfb4ac8a64537a0b3a93fcde3cdad9f1ce58b\
+			26751f67a3cbb140b1808cf187a4f4dfc04b05357c5d1c0eeac4c66f9ff7f2e6");
+		assert_eq!(enc.CFB_decrypt(&key, &iv, CfbSegment::Block), plain);
+		// the last segment can be shorter
+		assert_eq!(plain.cut(20).CFB_encrypt(&key, &iv, CfbSegment::Block), enc.cut(20));
+	}
+
+	#[test]
+	fn sp800_38a_ofb() {
+		let (key, iv, plain) = vectors();
+		let enc = plain.OFB_encrypt(&key, &iv);
+		assert_eq!(enc.to_hex(), "3b3fd92eb72dad20333449f8e83cfb4a7789508d16918f03f53c52dac54ed825\
+			9740051e9c5fecf64344f7a82260edcc304c6528f659c77866a510d9c1d6ae5e");
+		assert_eq!(enc.OFB_decrypt(&key, &iv), plain);
+		assert_eq!(plain.cut(20).OFB_encrypt(&key, &iv), enc.cut(20));
+	}
+
+	#[test]
+	fn pcbc_round_trip() {
+		let key = CryptoData::random(16);
+		let iv = CryptoData::random(16);
+		let aes = Aes::new(&key).unwrap();
+		for &len in [0, 5, 16, 40].iter() {
+			let plain = CryptoData::random(len);
+			let enc = plain.PCBC_encrypt(&key, &iv);
+			assert_eq!(enc.PCBC_decrypt(&key, &iv), plain.pad(16));
+			// the first block is the same as in CBC
+			assert_eq!(enc.cut(16), plain.CBC_encrypt(&key, &iv).cut(16));
+
+			let enc = plain.PCBC_encrypt_padded(&aes, &iv, &Iso10126).unwrap();
+			assert_eq!(enc.PCBC_decrypt_padded(&aes, &iv, &Iso10126).unwrap(), plain);
+		}
+	}
+
+	#[test]
+	fn pcbc_propagates_errors() {
+		let key = CryptoData::random(16);
+		let iv = CryptoData::random(16);
+		let mut enc = CryptoData::random(48).PCBC_encrypt(&key, &iv).into_vec();
+		enc[0] ^= 1;
+		let dec = CryptoData::from_owned(enc).PCBC_decrypt(&key, &iv);
+		// unlike CBC, every block after the modified one is garbled
+		assert!(dec.slice(48, 64) != CryptoData::from_slice(&[16; 16]));
+	}
+
+	#[test]
+	fn key_and_iv_length() {
+		let (key, iv, plain) = vectors();
+		let bad_key = key.cut(10);
+		let bad_iv = iv.cut(8);
+		let aes = Aes::new(&key).unwrap();
+
+		let results = vec![
+			plain.try_CFB_encrypt(&bad_key, &iv, CfbSegment::Bits8),
+			plain.try_CFB_decrypt(&bad_key, &iv, CfbSegment::Block),
+			plain.try_OFB_encrypt(&bad_key, &iv),
+			plain.try_OFB_decrypt(&bad_key, &iv),
+			plain.try_PCBC_encrypt(&bad_key, &iv),
+			plain.try_PCBC_decrypt(&bad_key, &iv),
+			plain.try_CBC_encrypt(&bad_key, &iv),
+			plain.try_CBC_decrypt(&bad_key, &iv),
+		];
+		for res in results {
+			assert!(matches!(res, Err(CryptoError::KeyLength(10))));
+		}
+
+		let results = vec![
+			plain.try_CFB_encrypt(&key, &bad_iv, CfbSegment::Bits8),
+			plain.try_CFB_decrypt(&key, &bad_iv, CfbSegment::Block),
+			plain.try_OFB_encrypt(&key, &bad_iv),
+			plain.try_OFB_decrypt(&key, &bad_iv),
+			plain.try_PCBC_encrypt(&key, &bad_iv),
+			plain.try_PCBC_decrypt(&key, &bad_iv),
+			plain.try_CBC_encrypt(&key, &bad_iv),
+			plain.try_CBC_decrypt(&key, &bad_iv),
+			plain.try_CBC_encrypt_with(&aes, &bad_iv),
+			plain.try_CBC_decrypt_with(&aes, &bad_iv),
+			plain.CBC_encrypt_padded(&aes, &bad_iv, &Iso10126),
+			plain.CBC_decrypt_padded(&aes, &bad_iv, &Iso10126),
+		];
+		for res in results {
+			assert!(matches!(res, Err(CryptoError::IvLength(8))));
+		}
 
-		CryptoData::from_vec(&digest.to_vec())
+		assert!(matches!(plain.cut(20).try_PCBC_decrypt(&key, &iv), Err(CryptoError::BlockLength(20, 16))));
+		assert!(matches!(plain.cut(20).try_CBC_decrypt(&key, &iv), Err(CryptoError::BlockLength(20, 16))));
 	}
 }
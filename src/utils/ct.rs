@@ -0,0 +1,91 @@
+// Constant-time helpers, the running time depends only on the lengths
+// of the inputs, never on their contents
+
+// 0xff if a < b, 0 otherwise, for a, b < 2^31
+fn ct_lt_mask(a: u32, b: u32) -> u8 {
+	(a.wrapping_sub(b) >> 31) as u8 * 0xff
+}
+
+pub fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+	// the length isn't secret
+	if a.len() != b.len() {
+		return false;
+	}
+	let mut diff = 0u8;
+	for (x, y) in a.iter().zip(b.iter()) {
+		diff |= *x ^ *y;
+	}
+	diff == 0
+}
+
+// PKCS#7 check that looks at the whole last block regardless of its contents
+pub fn ct_pkcs7_verify(data: &[u8], bsize: usize) -> bool {
+	let len = data.len();
+	if !(0 < bsize && bsize < 256) || len == 0 || !len.is_multiple_of(bsize) {
+		return false;
+	}
+
+	let pad_byte = data[len - 1];
+	let pad_size = pad_byte as u32;
+	// pad_size has to be in 1..=bsize
+	let mut bad = !ct_lt_mask(0, pad_size) | !ct_lt_mask(pad_size, bsize as u32 + 1);
+
+	for i in 0..bsize {
+		// check only the bytes within the padding
+		let in_pad = ct_lt_mask(i as u32, pad_size);
+		bad |= in_pad & (data[len - 1 - i] ^ pad_byte);
+	}
+	bad == 0
+}
+
+#[cfg(test)]
+mod tests {
+	use utils::ct::{ct_eq, ct_pkcs7_verify};
+	use utils::padding::{Padding, Pkcs7};
+
+	fn same_as_pkcs7(data: &[u8], bsize: usize) {
+		assert_eq!(ct_pkcs7_verify(data, bsize), Pkcs7.unpadded_len(data, bsize).is_ok(), "{:?}", data);
+	}
+
+	#[test]
+	fn every_last_byte() {
+		for last in 0..256 {
+			let mut data = vec![last as u8; 32];
+			same_as_pkcs7(&data, 16);
+			data[16] = 0x41;
+			same_as_pkcs7(&data, 16);
+		}
+	}
+
+	#[test]
+	fn corrupted_pad_bytes() {
+		for pad_size in 1..=16 {
+			let mut data = vec![0x41u8; 32 - pad_size];
+			Pkcs7.pad(&mut data, 16);
+			assert!(ct_pkcs7_verify(&data, 16));
+			for i in 16..32 {
+				let mut bad = data.clone();
+				bad[i] ^= 0x01;
+				same_as_pkcs7(&bad, 16);
+			}
+		}
+	}
+
+	#[test]
+	fn bad_lengths() {
+		assert!(!ct_pkcs7_verify(&[], 16));
+		assert!(!ct_pkcs7_verify(&[1u8; 15], 16));
+		assert!(!ct_pkcs7_verify(&[1u8; 16], 0));
+		assert!(!ct_pkcs7_verify(&[1u8; 256], 256));
+	}
+
+	#[test]
+	fn eq() {
+		assert!(ct_eq(b"", b""));
+		assert!(ct_eq(b"YELLOW SUBMARINE", b"YELLOW SUBMARINE"));
+		assert!(!ct_eq(b"YELLOW SUBMARINE", b"YELLOW SUBMARINF"));
+		assert!(!ct_eq(b"YELLOW SUBMARINE", b"xELLOW SUBMARINE"));
+		assert!(!ct_eq(b"YELLOW SUBMARINE", b"YELLOW SUBMARIN"));
+		assert!(!ct_eq(b"", b"Y"));
+	}
+}
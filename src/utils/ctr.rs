@@ -0,0 +1,260 @@
+use utils::blockcipher::BlockCipher;
+use utils::cryptodata::{xor_slice, CryptoError};
+
+// Where the counter lives in the counter block and how it's incremented
+#[derive (Clone,Copy,Debug,PartialEq,Eq)]
+pub enum CounterLayout {
+	// nonce followed by a little-endian 64 bit counter (cryptopals)
+	LittleEndian64,
+	// nonce followed by a big-endian 64 bit counter
+	BigEndian64,
+	// big-endian 32 bit counter in the last 4 bytes (GCM inc32)
+	BigEndian32,
+	// the whole block is a big-endian counter (NIST SP 800-38A)
+	BigEndian128,
+}
+
+impl CounterLayout {
+	// size of the counter field, the rest of the block is the nonce
+	pub fn counter_len(&self, bsize: usize) -> usize {
+		match *self {
+			CounterLayout::LittleEndian64 | CounterLayout::BigEndian64 => 8,
+			CounterLayout::BigEndian32 => 4,
+			CounterLayout::BigEndian128 => bsize,
+		}
+	}
+}
+
+#[derive (Clone,Debug,PartialEq,Eq)]
+pub struct CounterBlock {
+	block: Vec<u8>,
+	layout: CounterLayout,
+}
+
+impl CounterBlock {
+	// start from a complete initial counter block
+	pub fn from_block(block: &[u8], layout: CounterLayout) -> Result<CounterBlock, CryptoError> {
+		if block.len() < layout.counter_len(block.len()) {
+			return Err(CryptoError::NonceLength(block.len()));
+		}
+		Ok(CounterBlock { block: block.to_vec(), layout })
+	}
+
+	// nonce || counter, the counter is written with the layout's endianness
+	// into the bytes after the nonce
+	pub fn from_nonce(nonce: &[u8], counter: u64, layout: CounterLayout, bsize: usize) -> Result<CounterBlock, CryptoError> {
+		let ctr_len = bsize.checked_sub(nonce.len())
+			.ok_or(CryptoError::NonceLength(nonce.len()))?;
+		let valid = match layout {
+			CounterLayout::BigEndian128 => ctr_len >= 8,
+			_ => ctr_len == layout.counter_len(bsize),
+		};
+		if !valid {
+			return Err(CryptoError::NonceLength(nonce.len()));
+		}
+		// 32 bit counters can't hold more
+		if ctr_len < 8 && counter >> (8 * ctr_len) != 0 {
+			return Err(CryptoError::CounterOverflow);
+		}
+
+		let mut block = nonce.to_vec();
+		let mut ctr_bytes = counter.to_le_bytes()[..ctr_len.min(8)].to_vec();
+		ctr_bytes.resize(ctr_len, 0);
+		if layout != CounterLayout::LittleEndian64 {
+			ctr_bytes.reverse();
+		}
+		block.extend_from_slice(&ctr_bytes);
+
+		Ok(CounterBlock { block, layout })
+	}
+
+	pub fn block(&self) -> &[u8] {
+		&self.block
+	}
+
+	pub fn layout(&self) -> CounterLayout {
+		self.layout
+	}
+
+	// add n to the counter, wrapping around within the counter field
+	pub fn advance(&mut self, n: u64) {
+		let bsize = self.block.len();
+		let ctr_len = self.layout.counter_len(bsize);
+		let field = &mut self.block[bsize - ctr_len..];
+
+		let mut carry = n as u128;
+		for i in 0..ctr_len {
+			if carry == 0 {
+				break;
+			}
+			// least significant byte first
+			let idx = match self.layout {
+				CounterLayout::LittleEndian64 => i,
+				_ => ctr_len - 1 - i,
+			};
+			let sum = field[idx] as u128 + (carry & 0xff);
+			field[idx] = sum as u8;
+			carry = (carry >> 8) + (sum >> 8);
+		}
+	}
+
+	pub fn increment(&mut self) {
+		self.advance(1);
+	}
+}
+
+// CTR keystream with random access, any part of it can be generated
+// without computing the blocks before it
+pub struct CtrKeystream<C: BlockCipher> {
+	cipher: C,
+	start: CounterBlock,
+}
+
+impl<C: BlockCipher> CtrKeystream<C> {
+	pub fn new(cipher: C, start: CounterBlock) -> CtrKeystream<C> {
+		assert!(start.block().len() == cipher.block_size());
+		CtrKeystream { cipher, start }
+	}
+
+	// xor data with the keystream starting at byte offset
+	pub fn apply(&self, offset: usize, data: &mut [u8]) {
+		let bsize = self.cipher.block_size();
+		let mut ctr = self.start.clone();
+		ctr.advance((offset / bsize) as u64);
+
+		let mut keystream = vec![0u8; bsize];
+		// skip to the offset within the first block
+		let mut skip = offset % bsize;
+		let mut pos = 0;
+
+		while pos < data.len() {
+			keystream.copy_from_slice(ctr.block());
+			self.cipher.encrypt_block(&mut keystream);
+
+			let count = (bsize - skip).min(data.len() - pos);
+			xor_slice(&mut data[pos..pos + count], &keystream[skip..]);
+			pos += count;
+			skip = 0;
+			ctr.increment();
+		}
+	}
+
+	pub fn keystream(&self, offset: usize, len: usize) -> Vec<u8> {
+		let mut res = vec![0u8; len];
+		self.apply(offset, &mut res);
+		res
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use utils::aes::Aes;
+	use utils::ctr::{CounterBlock, CounterLayout, CtrKeystream};
+	use utils::cryptodata::{CryptoData, CryptoError};
+
+	// NIST SP 800-38A F.5, the whole block is the counter
+	const INIT_COUNTER: &str = "f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff";
+	const PLAIN: &str = "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+		30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710";
+
+	fn check_sp800_38a(key: &str, expected: &str) {
+		let aes = Aes::new(&CryptoData::from_hex(key)).unwrap();
+		let ctr = CounterBlock::from_block(&CryptoData::from_hex(INIT_COUNTER), CounterLayout::BigEndian128).unwrap();
+		let plain = CryptoData::from_hex(PLAIN);
+
+		let enc = plain.CTR_encrypt_counter(&aes, ctr.clone());
+		assert_eq!(enc.to_hex(), expected);
+		assert_eq!(enc.CTR_decrypt_counter(&aes, ctr.clone()), plain);
+
+		// random access into the same keystream
+		let keystream = CtrKeystream::new(aes, ctr);
+		let tail = CryptoData::from_owned(keystream.keystream(21, 30)).xor(&plain.slice(21, 51));
+		assert_eq!(tail, enc.slice(21, 51));
+	}
+
+	#[test]
+	fn sp800_38a_ctr_aes128() {
+		check_sp800_38a("2b7e151628aed2a6abf7158809cf4f3c",
+			"874d6191b620e3261bef6864990db6ce9806f66b7970fdff8617187bb9fffdff\
+			5ae4df3edbd5d35e5b4f09020db03eab1e031dda2fbe03d1792170a0f3009cee");
+	}
+
+	#[test]
+	fn sp800_38a_ctr_aes192() {
+		check_sp800_38a("8e73b0f7da0e6452c810f32b809079e562f8ead2522c6b7b",
+			"1abc932417521ca24f2b0459fe7e6e0b090339ec0aa6faefd5ccc2c6f4ce8e94\
+			1e36b26bd1ebc670d1bd1d665620abf74f78a7f6d29809585a97daec58c6b050");
+	}
+
+	#[test]
+	fn sp800_38a_ctr_aes256() {
+		check_sp800_38a("603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4",
+			"601ec313775789a5b7a7f504bbf3d228f443e3ca4d62b59aca84e990cacaf5c5\
+			2b0930daa23de94ce87017ba2d84988ddfc9c58db67aada613c2dd08457941a6");
+	}
+
+	fn incremented(block: &str, layout: CounterLayout) -> String {
+		let mut ctr = CounterBlock::from_block(&CryptoData::from_hex(block), layout).unwrap();
+		ctr.increment();
+		CryptoData::from_slice(ctr.block()).to_hex()
+	}
+
+	#[test]
+	fn inc32_wraps_within_last_word() {
+		assert_eq!(incremented("0102030405060708090a0b0cffffffff", CounterLayout::BigEndian32),
+			"0102030405060708090a0b0c00000000");
+		assert_eq!(incremented("0102030405060708090a0b0c0000ffff", CounterLayout::BigEndian32),
+			"0102030405060708090a0b0c00010000");
+	}
+
+	#[test]
+	fn be64_wraps_within_counter() {
+		assert_eq!(incremented("0102030405060708ffffffffffffffff", CounterLayout::BigEndian64),
+			"01020304050607080000000000000000");
+		assert_eq!(incremented("010203040506070800000000ffffffff", CounterLayout::BigEndian64),
+			"01020304050607080000000100000000");
+	}
+
+	#[test]
+	fn be128_wraps_whole_block() {
+		assert_eq!(incremented("ffffffffffffffffffffffffffffffff", CounterLayout::BigEndian128),
+			"00000000000000000000000000000000");
+		assert_eq!(incremented("0000000000000000ffffffffffffffff", CounterLayout::BigEndian128),
+			"00000000000000010000000000000000");
+	}
+
+	#[test]
+	fn le64_wraps_within_counter() {
+		assert_eq!(incremented("0102030405060708ffffffffffffffff", CounterLayout::LittleEndian64),
+			"01020304050607080000000000000000");
+		assert_eq!(incremented("0102030405060708ff00000000000000", CounterLayout::LittleEndian64),
+			"01020304050607080001000000000000");
+	}
+
+	#[test]
+	fn advance_carries() {
+		let mut ctr = CounterBlock::from_nonce(&[0u8; 12], 0xfffffff0, CounterLayout::BigEndian32, 16).unwrap();
+		ctr.advance(0x20);
+		assert_eq!(CryptoData::from_slice(ctr.block()).to_hex(), "00000000000000000000000000000010");
+	}
+
+	#[test]
+	fn wrong_nonce_length() {
+		for &(len, layout) in [(12, CounterLayout::LittleEndian64), (4, CounterLayout::BigEndian32),
+			(10, CounterLayout::BigEndian128), (17, CounterLayout::BigEndian64)].iter() {
+			assert!(match CounterBlock::from_nonce(&vec![0u8; len], 0, layout, 16) {
+				Err(CryptoError::NonceLength(l)) => l == len,
+				_ => false,
+			});
+		}
+	}
+
+	#[test]
+	fn counter_overflow() {
+		assert!(CounterBlock::from_nonce(&[0u8; 12], 0xffffffff, CounterLayout::BigEndian32, 16).is_ok());
+		assert!(matches!(CounterBlock::from_nonce(&[0u8; 12], 0x100000000, CounterLayout::BigEndian32, 16),
+			Err(CryptoError::CounterOverflow)));
+		// 64 bit counters take anything
+		assert!(CounterBlock::from_nonce(&[0u8; 8], u64::MAX, CounterLayout::BigEndian64, 16).is_ok());
+	}
+}
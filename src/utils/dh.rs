@@ -0,0 +1,208 @@
+// Finite field Diffie-Hellman
+
+use openssl::bn::BigNum;
+use openssl::error::ErrorStack;
+
+use utils::bignum::BigInt;
+use utils::cryptodata::{CryptoData, CryptoError};
+use utils::hash::Hash;
+
+#[derive (Clone, Debug, PartialEq, Eq)]
+pub struct DhGroup {
+	pub p: BigInt,
+	pub g: BigInt,
+	// order of the subgroup generated by g, if known
+	pub q: Option<BigInt>,
+}
+
+impl DhGroup {
+	pub fn new(p: BigInt, g: BigInt) -> DhGroup {
+		DhGroup { p, g, q: None }
+	}
+
+	// p = 2q + 1 with g generating the subgroup of order q
+	pub fn safe_prime(p: BigInt, g: BigInt) -> DhGroup {
+		let q = &(&p - &BigInt::one()) >> 1;
+		DhGroup { p, g, q: Some(q) }
+	}
+
+	// the toy group from chal33
+	pub fn toy() -> DhGroup {
+		DhGroup::new(BigInt::from_u32(37), BigInt::from_u32(5))
+	}
+
+	// the NIST prime used by the challenges is the 1536-bit one of RFC 3526
+	pub fn nist() -> DhGroup {
+		DhGroup::rfc3526(1536).unwrap()
+	}
+
+	// RFC 3526 MODP groups by the size of the prime in bits
+	pub fn rfc3526(bits: usize) -> Option<DhGroup> {
+		let p: Result<BigNum, ErrorStack> = match bits {
+			1536 => BigNum::get_rfc3526_prime_1536(),
+			2048 => BigNum::get_rfc3526_prime_2048(),
+			3072 => BigNum::get_rfc3526_prime_3072(),
+			4096 => BigNum::get_rfc3526_prime_4096(),
+			6144 => BigNum::get_rfc3526_prime_6144(),
+			8192 => BigNum::get_rfc3526_prime_8192(),
+			_ => return None,
+		};
+		let p = p.expect("BigNum allocation failed");
+		Some(DhGroup::safe_prime(BigInt::from(p), BigInt::from_u32(2)))
+	}
+
+	// byte length of the group elements
+	pub fn element_len(&self) -> usize {
+		self.p.bits().div_ceil(8)
+	}
+
+	pub fn generate_keypair(&self) -> DhKeyPair {
+		// 2..p-1
+		let private = BigInt::random_range(&BigInt::from_u32(2), &(&self.p - &BigInt::one()));
+		DhKeyPair::from_private(self.clone(), private)
+	}
+
+	// reject 0, 1, p-1, values outside the group and, when q is known,
+	// values outside the subgroup of order q
+	pub fn validate_public(&self, public: &BigInt) -> Result<(), CryptoError> {
+		let p_1 = &self.p - &BigInt::one();
+		if *public <= BigInt::one() || *public >= p_1 {
+			return Err(CryptoError::PublicKey);
+		}
+		if let Some(ref q) = self.q {
+			if !public.modexp(q, &self.p).is_one() {
+				return Err(CryptoError::PublicKey);
+			}
+		}
+		Ok(())
+	}
+}
+
+#[derive (Clone, Debug)]
+pub struct DhKeyPair {
+	group: DhGroup,
+	private: BigInt,
+	public: BigInt,
+	// check the other party's public key before using it
+	validate: bool,
+}
+
+impl DhKeyPair {
+	pub fn from_private(group: DhGroup, private: BigInt) -> DhKeyPair {
+		let public = group.g.modexp(&private, &group.p);
+		DhKeyPair { group, private, public, validate: true }
+	}
+
+	pub fn group(&self) -> &DhGroup {
+		&self.group
+	}
+
+	pub fn public(&self) -> &BigInt {
+		&self.public
+	}
+
+	pub fn private(&self) -> &BigInt {
+		&self.private
+	}
+
+	// turn the public key checks off to demonstrate the attacks
+	pub fn set_validation(&mut self, validate: bool) {
+		self.validate = validate;
+	}
+
+	pub fn shared_secret(&self, other_public: &BigInt) -> Result<BigInt, CryptoError> {
+		if self.validate {
+			self.group.validate_public(other_public)?;
+		}
+		Ok(other_public.modexp(&self.private, &self.group.p))
+	}
+
+	// shared secret straight to a symmetric key
+	pub fn shared_key<H: Hash>(&self, other_public: &BigInt, key_len: usize) -> Result<CryptoData, CryptoError> {
+		let secret = self.shared_secret(other_public)?;
+		derive_key::<H>(&secret, key_len)
+	}
+}
+
+// first key_len bytes of H(secret), with the secret as big-endian bytes
+// without leading zeros
+pub fn derive_key<H: Hash>(secret: &BigInt, key_len: usize) -> Result<CryptoData, CryptoError> {
+	if key_len > H::DIGEST_SIZE {
+		return Err(CryptoError::KeyLength(key_len));
+	}
+	let digest = H::hash(&secret.to_bytes());
+	Ok(CryptoData::from_slice(&digest[..key_len]))
+}
+
+#[cfg(test)]
+mod tests {
+	use utils::bignum::BigInt;
+	use utils::cryptodata::CryptoError;
+	use utils::dh::{derive_key, DhGroup};
+	use utils::sha1::Sha1;
+	use utils::sha2::Sha256;
+
+	fn rejected(group: &DhGroup, public: &BigInt) -> bool {
+		matches!(group.validate_public(public), Err(CryptoError::PublicKey))
+	}
+
+	#[test]
+	fn nist_is_rfc3526_group_5() {
+		let nist = DhGroup::nist();
+		assert_eq!(nist.p.bits(), 1536);
+		assert!(nist.p.to_hex().starts_with("ffffffffffffffffc90fdaa22168c234"));
+		assert_eq!(nist.g, BigInt::from_u32(2));
+		assert_eq!(nist.element_len(), 192);
+	}
+
+	#[test]
+	fn validate_public() {
+		let group = DhGroup::nist();
+		let one = BigInt::one();
+		let p_1 = &group.p - &one;
+		for public in [BigInt::zero(), one.clone(), p_1.clone(), group.p.clone(), &group.p + &one].iter() {
+			assert!(rejected(&group, public));
+		}
+		assert!(group.validate_public(group.generate_keypair().public()).is_ok());
+		// the subgroup of order q are the squares, p = 7 mod 8 so 2 is one
+		// and -2 isn't
+		assert!(group.validate_public(&BigInt::from_u32(2)).is_ok());
+		assert!(rejected(&group, &(&group.p - &BigInt::from_u32(2))));
+
+		// without q only the degenerate values are caught
+		let toy = DhGroup::toy();
+		assert!(rejected(&toy, &BigInt::from_u32(36)));
+		assert!(toy.validate_public(&BigInt::from_u32(35)).is_ok());
+	}
+
+	#[test]
+	fn shared_secret_matches() {
+		let groups = vec![DhGroup::toy(), DhGroup::nist(), DhGroup::rfc3526(2048).unwrap()];
+		for group in groups {
+			let a = group.generate_keypair();
+			let b = group.generate_keypair();
+			let s_a = a.shared_secret(b.public()).unwrap();
+			assert_eq!(s_a, b.shared_secret(a.public()).unwrap());
+			assert_eq!(a.shared_key::<Sha256>(b.public(), 32).unwrap(), derive_key::<Sha256>(&s_a, 32).unwrap());
+		}
+		assert!(DhGroup::rfc3526(1024).is_none());
+	}
+
+	#[test]
+	fn validation_can_be_turned_off() {
+		let group = DhGroup::nist();
+		let mut a = group.generate_keypair();
+		assert!(matches!(a.shared_secret(&group.p), Err(CryptoError::PublicKey)));
+		a.set_validation(false);
+		assert!(a.shared_secret(&group.p).unwrap().is_zero());
+		assert!(a.shared_secret(&BigInt::one()).unwrap().is_one());
+	}
+
+	#[test]
+	fn derive_key_length() {
+		let secret = BigInt::from_u32(1234);
+		assert_eq!(derive_key::<Sha1>(&secret, 20).unwrap().len(), 20);
+		assert_eq!(derive_key::<Sha1>(&secret, 16).unwrap(), derive_key::<Sha1>(&secret, 20).unwrap().cut(16));
+		assert!(matches!(derive_key::<Sha1>(&secret, 21), Err(CryptoError::KeyLength(21))));
+	}
+}
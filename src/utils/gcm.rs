@@ -0,0 +1,179 @@
+use utils::blockcipher::BlockCipher;
+
+// GF(2^128) as used by GCM: elements are 16 byte blocks read as big-endian
+// integers, the bit order is reflected (the first bit is the x^0 coefficient)
+pub type Gf128 = u128;
+
+// x^128 + x^7 + x^2 + x + 1, reflected
+const R: Gf128 = 0xe1 << 120;
+
+pub fn gf128_from_bytes(bytes: &[u8]) -> Gf128 {
+	assert!(bytes.len() <= 16);
+	let mut block = [0u8; 16];
+	block[..bytes.len()].copy_from_slice(bytes);
+	u128::from_be_bytes(block)
+}
+
+pub fn gf128_to_bytes(x: Gf128) -> [u8; 16] {
+	x.to_be_bytes()
+}
+
+pub fn gf128_mul(x: Gf128, y: Gf128) -> Gf128 {
+	let mut z = 0;
+	let mut v = y;
+	for i in 0..128 {
+		if (x >> (127 - i)) & 1 == 1 {
+			z ^= v;
+		}
+		v = if v & 1 == 1 { (v >> 1) ^ R } else { v >> 1 };
+	}
+	z
+}
+
+// Incremental GHASH with hash key H
+pub struct Ghash {
+	h: Gf128,
+	acc: Gf128,
+}
+
+impl Ghash {
+	pub fn new(h: &[u8]) -> Ghash {
+		Ghash { h: gf128_from_bytes(h), acc: 0 }
+	}
+
+	// absorb data, the last block is zero padded
+	pub fn update(&mut self, data: &[u8]) {
+		for block in data.chunks(16) {
+			self.acc = gf128_mul(self.acc ^ gf128_from_bytes(block), self.h);
+		}
+	}
+
+	pub fn finish(&self) -> [u8; 16] {
+		gf128_to_bytes(self.acc)
+	}
+}
+
+// GHASH(H, A, C) with the length block at the end
+pub fn ghash(h: &[u8], aad: &[u8], ciphertext: &[u8]) -> [u8; 16] {
+	let mut g = Ghash::new(h);
+	g.update(aad);
+	g.update(ciphertext);
+
+	let mut lengths = [0u8; 16];
+	lengths[..8].copy_from_slice(&((aad.len() as u64) * 8).to_be_bytes());
+	lengths[8..].copy_from_slice(&((ciphertext.len() as u64) * 8).to_be_bytes());
+	g.update(&lengths);
+	g.finish()
+}
+
+// the hash key, encryption of the zero block
+pub fn hash_key<C: BlockCipher>(cipher: &C) -> [u8; 16] {
+	assert!(cipher.block_size() == 16);
+	let mut h = [0u8; 16];
+	cipher.encrypt_block(&mut h);
+	h
+}
+
+// the pre-counter block J0
+pub fn pre_counter(h: &[u8], iv: &[u8]) -> [u8; 16] {
+	if iv.len() == 12 {
+		let mut j0 = [0u8; 16];
+		j0[..12].copy_from_slice(iv);
+		j0[15] = 1;
+		j0
+	} else {
+		ghash(h, &[], iv)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use utils::cryptodata::{CryptoData, CryptoError};
+
+	// McGrew and Viega, "The Galois/Counter Mode of Operation", test cases 1-6
+	const K3: &str = "feffe9928665731c6d6a8f9467308308";
+	const IV3: &str = "cafebabefacedbaddecaf888";
+	const P3: &str = "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a72\
+		1c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b391aafd255";
+	const C3: &str = "42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e\
+		21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e091473f5985";
+	const AAD4: &str = "feedfacedeadbeeffeedfacedeadbeefabaddad2";
+
+	fn check(key: &str, iv: &str, plain: &str, aad: &str, cipher: &str, tag: &str) {
+		let (key, iv) = (CryptoData::from_hex(key), CryptoData::from_hex(iv));
+		let (plain, aad) = (CryptoData::from_hex(plain), CryptoData::from_hex(aad));
+
+		let (enc, t) = plain.GCM_encrypt(&key, &iv, &aad, 16).unwrap();
+		assert_eq!(enc.to_hex(), cipher);
+		assert_eq!(t.to_hex(), tag);
+		assert_eq!(enc.GCM_decrypt(&key, &iv, &aad, &t).unwrap(), plain);
+	}
+
+	#[test]
+	fn test_case_1() {
+		check("00000000000000000000000000000000", "000000000000000000000000", "", "",
+			"", "58e2fccefa7e3061367f1d57a4e7455a");
+	}
+
+	#[test]
+	fn test_case_2() {
+		check("00000000000000000000000000000000", "000000000000000000000000", "00000000000000000000000000000000", "",
+			"0388dace60b6a392f328c2b971b2fe78", "ab6e47d42cec13bdf53a67b21257bddf");
+	}
+
+	#[test]
+	fn test_case_3() {
+		check(K3, IV3, P3, "", C3, "4d5c2af327cd64a62cf35abd2ba6fab4");
+	}
+
+	#[test]
+	fn test_case_4() {
+		// 60 bytes of plaintext with associated data
+		check(K3, IV3, &P3[..120], AAD4, &C3[..120], "5bc94fbc3221a5db94fae95ae7121a47");
+	}
+
+	#[test]
+	fn test_case_5() {
+		// 64 bit IV, goes through GHASH
+		check(K3, "cafebabefacedbad", &P3[..120], AAD4,
+			"61353b4c2806934a777ff51fa22a4755699b2a714fcdc6f83766e5f97b6c7423\
+			73806900e49f24b22b097544d4896b424989b5e1ebac0f07c23f4598",
+			"3612d2e79e3b0785561be14aaca2fccb");
+	}
+
+	#[test]
+	fn test_case_6() {
+		// 480 bit IV
+		check(K3, "9313225df88406e555909c5aff5269aa6a7a9538534f7da1e4c303d2a318a728\
+			c3c0c95156809539fcf0e2429a6b525416aedbf5a0de6a57a637b39b", &P3[..120], AAD4,
+			"8ce24998625615b603a033aca13fb894be9112a5c3a211a8ba262a3cca7e2ca7\
+			01e4a9a4fba43c90ccdcb281d48c7c6fd62875d2aca417034c34aee5",
+			"619cc5aefffe0bfa462af43c1699d050");
+	}
+
+	#[test]
+	fn truncated_tag() {
+		let (key, iv) = (CryptoData::from_hex(K3), CryptoData::from_hex(IV3));
+		let (plain, aad) = (CryptoData::from_hex(&P3[..120]), CryptoData::from_hex(AAD4));
+
+		let (enc, tag) = plain.GCM_encrypt(&key, &iv, &aad, 12).unwrap();
+		assert_eq!(tag.to_hex(), "5bc94fbc3221a5db94fae95a");
+		assert_eq!(enc.GCM_decrypt(&key, &iv, &aad, &tag).unwrap(), plain);
+
+		assert!(matches!(plain.GCM_encrypt(&key, &iv, &aad, 3), Err(CryptoError::TagLength(3))));
+	}
+
+	#[test]
+	fn modified_tag_is_rejected() {
+		let (key, iv) = (CryptoData::from_hex(K3), CryptoData::from_hex(IV3));
+		let (plain, aad) = (CryptoData::from_hex(&P3[..120]), CryptoData::from_hex(AAD4));
+		let (enc, tag) = plain.GCM_encrypt(&key, &iv, &aad, 16).unwrap();
+
+		for i in 0..tag.len() {
+			let mut bad = tag.clone().into_vec();
+			bad[i] ^= 0x80;
+			let res = enc.GCM_decrypt(&key, &iv, &aad, &CryptoData::from_owned(bad));
+			assert!(matches!(res, Err(CryptoError::Authentication)));
+		}
+	}
+}
@@ -0,0 +1,56 @@
+// Common interface of the native hash functions
+
+use utils::cryptodata::CryptoError;
+
+pub trait Hash: Clone {
+	const BLOCK_SIZE: usize;
+	const DIGEST_SIZE: usize;
+
+	fn new() -> Self;
+	fn update(&mut self, data: &[u8]);
+	fn digest(&self) -> Vec<u8>;
+
+	fn hash(data: &[u8]) -> Vec<u8> {
+		let mut h = Self::new();
+		h.update(data);
+		h.digest()
+	}
+}
+
+// Merkle-Damgård hash whose chaining state can be set from a digest,
+// which is all that's needed for length extension
+pub trait InjectableHash: Hash {
+	// padding appended to a message of len bytes
+	fn md_padding(len: u64) -> Vec<u8>;
+
+	// Continue hashing after length bytes that produced the digest.
+	// Only whole blocks ever reach the chaining state, so length has to
+	// be a multiple of BLOCK_SIZE, i.e. include the padding. The
+	// from_state() constructors of the hashes follow the same rule and
+	// return a BlockLength error otherwise.
+	fn from_digest(digest: &[u8], length: u64) -> Result<Self, CryptoError>;
+}
+
+// Feed data through a block buffer, compress is called for every full block.
+// The buffer is left with the remaining partial block.
+pub fn md_update<F: FnMut(&[u8])>(buffer: &mut Vec<u8>, block_size: usize, data: &[u8], mut compress: F) {
+	let mut data = data;
+
+	// complete the buffered block first
+	if !buffer.is_empty() {
+		let take = (block_size - buffer.len()).min(data.len());
+		buffer.extend_from_slice(&data[..take]);
+		data = &data[take..];
+		if buffer.len() < block_size {
+			return;
+		}
+		compress(buffer);
+		buffer.clear();
+	}
+
+	let mut blocks = data.chunks_exact(block_size);
+	for block in &mut blocks {
+		compress(block);
+	}
+	buffer.extend_from_slice(blocks.remainder());
+}
@@ -0,0 +1,135 @@
+// HMAC (RFC 2104) over any of the native hash functions
+
+use utils::ct::ct_eq;
+use utils::hash::Hash;
+
+const IPAD: u8 = 0x36;
+const OPAD: u8 = 0x5c;
+
+#[derive (Clone)]
+pub struct Hmac<H: Hash> {
+	// already fed with key ^ ipad, updated with the message
+	inner: H,
+	// already fed with key ^ opad, hashes the inner digest at the end
+	outer: H,
+}
+
+impl<H: Hash> Hmac<H> {
+	pub fn new(key: &[u8]) -> Hmac<H> {
+		// keys longer than a block are hashed first, shorter ones zero padded
+		let mut block_key = if key.len() > H::BLOCK_SIZE {
+			H::hash(key)
+		} else {
+			key.to_vec()
+		};
+		block_key.resize(H::BLOCK_SIZE, 0);
+
+		let ipad: Vec<u8> = block_key.iter().map(|b| b ^ IPAD).collect();
+		let opad: Vec<u8> = block_key.iter().map(|b| b ^ OPAD).collect();
+
+		let mut inner = H::new();
+		inner.update(&ipad);
+		let mut outer = H::new();
+		outer.update(&opad);
+		Hmac { inner, outer }
+	}
+
+	pub fn update(&mut self, data: &[u8]) {
+		self.inner.update(data);
+	}
+
+	pub fn finish(&self) -> Vec<u8> {
+		let mut outer = self.outer.clone();
+		outer.update(&self.inner.digest());
+		outer.digest()
+	}
+
+	// compare with an expected tag without leaking where they differ
+	pub fn verify(&self, tag: &[u8]) -> bool {
+		ct_eq(&self.finish(), tag)
+	}
+}
+
+pub fn hmac<H: Hash>(key: &[u8], data: &[u8]) -> Vec<u8> {
+	let mut mac = Hmac::<H>::new(key);
+	mac.update(data);
+	mac.finish()
+}
+
+pub fn hmac_verify<H: Hash>(key: &[u8], data: &[u8], tag: &[u8]) -> bool {
+	let mut mac = Hmac::<H>::new(key);
+	mac.update(data);
+	mac.verify(tag)
+}
+
+#[cfg(test)]
+mod tests {
+	use utils::cryptodata::CryptoData;
+	use utils::hash::Hash;
+	use utils::hmac::{hmac, hmac_verify, Hmac};
+	use utils::sha1::Sha1;
+	use utils::sha2::Sha256;
+
+	fn check<H: Hash>(key: &[u8], data: &[u8], expected: &str) {
+		let mac = CryptoData::from_owned(hmac::<H>(key, data)).to_hex();
+		// truncated outputs in the RFCs are prefixes
+		assert_eq!(&mac[..expected.len()], expected);
+	}
+
+	// 0x01, 0x02, ..., 0x19
+	fn counting_key() -> Vec<u8> {
+		(1..26).collect()
+	}
+
+	const LONG_KEY_DATA: &[u8] = b"Test Using Larger Than Block-Size Key - Hash Key First";
+
+	// RFC 2202 section 3
+	#[test]
+	fn rfc2202_hmac_sha1() {
+		check::<Sha1>(&[0x0b; 20], b"Hi There", "b617318655057264e28bc0b6fb378c8ef146be00");
+		check::<Sha1>(b"Jefe", b"what do ya want for nothing?", "effcdf6ae5eb2fa2d27416d5f184df9c259a7c79");
+		check::<Sha1>(&[0xaa; 20], &[0xdd; 50], "125d7342b9ac11cd91a39af48aa17b4f63f175d3");
+		check::<Sha1>(&counting_key(), &[0xcd; 50], "4c9007f4026250c6bc8414f9bf50c86c2d7235da");
+		check::<Sha1>(&[0x0c; 20], b"Test With Truncation", "4c1a03424b55e07fe7f27be1");
+		check::<Sha1>(&[0xaa; 80], LONG_KEY_DATA, "aa4ae5e15272d00e95705637ce8a3b55ed402112");
+		check::<Sha1>(&[0xaa; 80], b"Test Using Larger Than Block-Size Key and Larger Than One Block-Size Data",
+			"e8e99d0f45237d786d6bbaa7965c7808bbff1a91");
+	}
+
+	// RFC 4231 section 4
+	#[test]
+	fn rfc4231_hmac_sha256() {
+		check::<Sha256>(&[0x0b; 20], b"Hi There", "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7");
+		check::<Sha256>(b"Jefe", b"what do ya want for nothing?", "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843");
+		check::<Sha256>(&[0xaa; 20], &[0xdd; 50], "773ea91e36800e46854db8ebd09181a72959098b3ef8c122d9635514ced565fe");
+		check::<Sha256>(&counting_key(), &[0xcd; 50], "82558a389a443c0ea4cc819899f2083a85f0faa3e578f8077a2e3ff46729665b");
+		check::<Sha256>(&[0x0c; 20], b"Test With Truncation", "a3b6167473100ee06e0c796c2955552b");
+		check::<Sha256>(&[0xaa; 131], LONG_KEY_DATA, "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54");
+		check::<Sha256>(&[0xaa; 131], b"This is a test using a larger than block-size key and a larger than block-size data. \
+			The key needs to be hashed before being used by the HMAC algorithm.",
+			"9b09ffa71b942fcb27635fbcd5b0e944bfdc63644f0713938a7f51535c3a35e2");
+	}
+
+	#[test]
+	fn incremental_update() {
+		let mut mac = Hmac::<Sha256>::new(b"Jefe");
+		mac.update(b"what do ya ");
+		mac.update(b"want for nothing?");
+		assert_eq!(mac.finish(), hmac::<Sha256>(b"Jefe", b"what do ya want for nothing?"));
+	}
+
+	#[test]
+	fn verify_rejects_modified_mac() {
+		let key = CryptoData::from_text("key");
+		let msg = CryptoData::from_text("The quick brown fox jumps over the lazy dog");
+		let mac = msg.HMAC::<Sha1>(&key);
+		assert!(msg.HMAC_verify::<Sha1>(&key, &mac));
+
+		for i in 0..mac.len() {
+			let mut bad = mac.clone().into_vec();
+			bad[i] ^= 0x01;
+			assert!(!msg.HMAC_verify::<Sha1>(&key, &CryptoData::from_owned(bad)));
+		}
+		assert!(!hmac_verify::<Sha1>(&key, &msg, &mac[..19]));
+	}
+}
@@ -0,0 +1,123 @@
+// Length extension of secret-prefix MACs, MAC = H(key || message),
+// for any Merkle-Damgård hash whose state can be set from a digest
+
+use std::ops::Range;
+
+use utils::cryptodata::{CryptoData, CryptoError};
+use utils::hash::InjectableHash;
+
+// Anything that can tell whether a MAC is valid for a message,
+// usually a server holding the key
+pub trait MacOracle {
+	fn verify(&self, message: &[u8], mac: &[u8]) -> bool;
+}
+
+impl<F: Fn(&[u8], &[u8]) -> bool> MacOracle for F {
+	fn verify(&self, message: &[u8], mac: &[u8]) -> bool {
+		self(message, mac)
+	}
+}
+
+// message || glue padding || suffix with its MAC, valid if the key is key_len bytes long
+#[derive (Clone, Debug)]
+pub struct Forgery {
+	pub key_len: usize,
+	pub message: CryptoData,
+	pub mac: CryptoData,
+}
+
+// forge the MAC of known_message || glue padding || suffix for a single key length
+// fails if known_mac isn't a digest of H
+pub fn length_extend_one<H: InjectableHash>(known_mac: &[u8], known_message: &[u8], suffix: &[u8], key_len: usize) -> Result<Forgery, CryptoError> {
+	// padding the server added after key || message
+	let glue = H::md_padding((key_len + known_message.len()) as u64);
+	let mut message = known_message.to_vec();
+	message.extend_from_slice(&glue);
+	message.extend_from_slice(suffix);
+
+	// continue hashing from the known MAC
+	let processed = (key_len + known_message.len() + glue.len()) as u64;
+	let mut h = H::from_digest(known_mac, processed)?;
+	h.update(suffix);
+
+	Ok(Forgery { key_len, message: CryptoData::from_owned(message), mac: CryptoData::from_owned(h.digest()) })
+}
+
+// one forgery candidate for every guessed key length
+pub fn length_extend<H: InjectableHash>(known_mac: &[u8], known_message: &[u8], suffix: &[u8], key_len_range: Range<usize>) -> Result<Vec<Forgery>, CryptoError> {
+	key_len_range
+		.map(|key_len| length_extend_one::<H>(known_mac, known_message, suffix, key_len))
+		.collect()
+}
+
+// query the oracle with the candidates until one is accepted,
+// Ok(None) if none of them is
+pub fn find_forgery<H: InjectableHash, O: MacOracle>(oracle: &O, known_mac: &[u8], known_message: &[u8], suffix: &[u8], key_len_range: Range<usize>) -> Result<Option<Forgery>, CryptoError> {
+	for key_len in key_len_range {
+		let forgery = length_extend_one::<H>(known_mac, known_message, suffix, key_len)?;
+		if oracle.verify(&forgery.message, &forgery.mac) {
+			return Ok(Some(forgery));
+		}
+	}
+	Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+	use rand::Rng;
+
+	use utils::cryptodata::{CryptoData, CryptoError};
+	use utils::hash::InjectableHash;
+	use utils::lenext::{find_forgery, length_extend, MacOracle};
+	use utils::md4::Md4;
+	use utils::sha1::Sha1;
+
+	const MESSAGE: &str = "comment1=cooking%20MCs;userdata=foo;comment2=%20like%20a%20pound%20of%20bacon";
+	const SUFFIX: &[u8] = b";admin=true";
+
+	// the key length is unknown to the attacker
+	fn forge<H: InjectableHash, F: Fn(&CryptoData, &CryptoData) -> CryptoData>(mac_prefix: F) {
+		let key = CryptoData::random(rand::rng().random_range(1..64));
+		let msg = CryptoData::from_text(MESSAGE);
+		let mac = mac_prefix(&msg, &key);
+		let oracle = |m: &[u8], t: &[u8]| mac_prefix(&CryptoData::from_slice(m), &key).ct_eq(&CryptoData::from_slice(t));
+
+		let forgery = find_forgery::<H, _>(&oracle, &mac, &msg, SUFFIX, 0..64).unwrap().unwrap();
+		assert_eq!(forgery.key_len, key.len());
+		assert!(forgery.message.starts_with(&msg));
+		assert!(forgery.message.ends_with(SUFFIX));
+		assert!(oracle.verify(&forgery.message, &forgery.mac));
+
+		// the key is longer than all the guesses
+		let none = find_forgery::<H, _>(&oracle, &mac, &msg, SUFFIX, 0..key.len()).unwrap();
+		assert!(none.is_none());
+	}
+
+	#[test]
+	fn sha1_forgery() {
+		forge::<Sha1, _>(|msg, key| msg.SHA1_mac_prefix(key));
+	}
+
+	#[test]
+	fn md4_forgery() {
+		forge::<Md4, _>(|msg, key| msg.MD4_mac_prefix(key));
+	}
+
+	#[test]
+	fn one_candidate_per_key_length() {
+		let msg = CryptoData::from_text(MESSAGE);
+		let mac = msg.SHA1_mac_prefix(&CryptoData::from_text("YELLOW SUBMARINE"));
+		let forgeries = length_extend::<Sha1>(&mac, &msg, SUFFIX, 10..20).unwrap();
+		let key_lens: Vec<usize> = forgeries.iter().map(|f| f.key_len).collect();
+		assert_eq!(key_lens, (10..20).collect::<Vec<usize>>());
+	}
+
+	#[test]
+	fn wrong_mac_size() {
+		let msg = CryptoData::from_text(MESSAGE);
+		let mac = msg.MD4_mac_prefix(&CryptoData::from_text("YELLOW SUBMARINE"));
+		let oracle = |_: &[u8], _: &[u8]| true;
+		assert!(matches!(find_forgery::<Sha1, _>(&oracle, &mac, &msg, SUFFIX, 0..64), Err(CryptoError::DigestLength(16))));
+		assert!(matches!(length_extend::<Sha1>(&mac, &msg, SUFFIX, 0..64), Err(CryptoError::DigestLength(16))));
+	}
+}
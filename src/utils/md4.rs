@@ -0,0 +1,274 @@
+// Native MD4 (RFC 1320) with access to the internal chaining state
+// and the round functions
+
+use utils::cryptodata::CryptoError;
+use utils::hash::{md_update, Hash, InjectableHash};
+
+pub const BLOCK_SIZE: usize = 64;
+pub const DIGEST_SIZE: usize = 16;
+
+const INIT_STATE: [u32; 4] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+
+#[derive (Clone)]
+pub struct Md4 {
+	state: [u32; 4],
+	// unprocessed data, always shorter than a block
+	buffer: Vec<u8>,
+	// number of bytes hashed so far, including the buffer
+	length: u64,
+}
+
+// Merkle-Damgård padding for a message of len bytes:
+// 0x80, zeros and the little-endian bit length
+pub fn md_padding(len: u64) -> Vec<u8> {
+	let mut pad = vec![0x80u8];
+	let zeros = (BLOCK_SIZE + 55 - (len as usize % BLOCK_SIZE)) % BLOCK_SIZE;
+	pad.resize(1 + zeros, 0);
+	pad.extend_from_slice(&(len.wrapping_mul(8)).to_le_bytes());
+	pad
+}
+
+// split a digest back into the chaining state
+pub fn state_from_digest(digest: &[u8]) -> [u32; 4] {
+	assert!(digest.len() == DIGEST_SIZE);
+	let mut state = [0u32; 4];
+	for (i, word) in digest.chunks(4).enumerate() {
+		state[i] = u32::from_le_bytes([word[0], word[1], word[2], word[3]]);
+	}
+	state
+}
+
+pub fn f(x: u32, y: u32, z: u32) -> u32 {
+	(x & y) | (!x & z)
+}
+
+pub fn g(x: u32, y: u32, z: u32) -> u32 {
+	(x & y) | (x & z) | (y & z)
+}
+
+pub fn h(x: u32, y: u32, z: u32) -> u32 {
+	x ^ y ^ z
+}
+
+// a = (a + F(b,c,d) + X[k]) <<< s
+pub fn round1_step(a: u32, b: u32, c: u32, d: u32, xk: u32, s: u32) -> u32 {
+	a.wrapping_add(f(b, c, d)).wrapping_add(xk).rotate_left(s)
+}
+
+// a = (a + G(b,c,d) + X[k] + 5A827999) <<< s
+pub fn round2_step(a: u32, b: u32, c: u32, d: u32, xk: u32, s: u32) -> u32 {
+	a.wrapping_add(g(b, c, d)).wrapping_add(xk).wrapping_add(0x5a827999).rotate_left(s)
+}
+
+// a = (a + H(b,c,d) + X[k] + 6ED9EBA1) <<< s
+pub fn round3_step(a: u32, b: u32, c: u32, d: u32, xk: u32, s: u32) -> u32 {
+	a.wrapping_add(h(b, c, d)).wrapping_add(xk).wrapping_add(0x6ed9eba1).rotate_left(s)
+}
+
+// message word order and shifts of each round
+pub const ROUND1_ORDER: [usize; 16] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+pub const ROUND2_ORDER: [usize; 16] = [0, 4, 8, 12, 1, 5, 9, 13, 2, 6, 10, 14, 3, 7, 11, 15];
+pub const ROUND3_ORDER: [usize; 16] = [0, 8, 4, 12, 2, 10, 6, 14, 1, 9, 5, 13, 3, 11, 7, 15];
+pub const ROUND1_SHIFTS: [u32; 4] = [3, 7, 11, 19];
+pub const ROUND2_SHIFTS: [u32; 4] = [3, 5, 9, 13];
+pub const ROUND3_SHIFTS: [u32; 4] = [3, 9, 11, 15];
+
+pub fn message_words(block: &[u8]) -> [u32; 16] {
+	assert!(block.len() == BLOCK_SIZE);
+	let mut x = [0u32; 16];
+	for (i, word) in block.chunks(4).enumerate() {
+		x[i] = u32::from_le_bytes([word[0], word[1], word[2], word[3]]);
+	}
+	x
+}
+
+pub fn compress(state: &mut [u32; 4], block: &[u8]) {
+	let x = message_words(block);
+	// the registers rotate: a, d, c, b are updated in turn
+	let mut r = *state;
+
+	for i in 0..16 {
+		let (a, b, c, d) = ((16 - i) % 4, (17 - i) % 4, (18 - i) % 4, (19 - i) % 4);
+		r[a] = round1_step(r[a], r[b], r[c], r[d], x[ROUND1_ORDER[i]], ROUND1_SHIFTS[i % 4]);
+	}
+	for i in 0..16 {
+		let (a, b, c, d) = ((16 - i) % 4, (17 - i) % 4, (18 - i) % 4, (19 - i) % 4);
+		r[a] = round2_step(r[a], r[b], r[c], r[d], x[ROUND2_ORDER[i]], ROUND2_SHIFTS[i % 4]);
+	}
+	for i in 0..16 {
+		let (a, b, c, d) = ((16 - i) % 4, (17 - i) % 4, (18 - i) % 4, (19 - i) % 4);
+		r[a] = round3_step(r[a], r[b], r[c], r[d], x[ROUND3_ORDER[i]], ROUND3_SHIFTS[i % 4]);
+	}
+
+	for i in 0..4 {
+		state[i] = state[i].wrapping_add(r[i]);
+	}
+}
+
+impl Md4 {
+	pub fn new() -> Md4 {
+		Md4 { state: INIT_STATE, buffer: Vec::with_capacity(BLOCK_SIZE), length: 0 }
+	}
+
+	pub fn from_state(state: [u32; 4], length: u64) -> Result<Md4, CryptoError> {
+		if length % BLOCK_SIZE as u64 != 0 {
+			return Err(CryptoError::BlockLength(length as usize, BLOCK_SIZE));
+		}
+		Ok(Md4 { state, buffer: Vec::with_capacity(BLOCK_SIZE), length })
+	}
+
+	pub fn state(&self) -> [u32; 4] {
+		self.state
+	}
+
+	pub fn length(&self) -> u64 {
+		self.length
+	}
+
+	pub fn update(&mut self, data: &[u8]) {
+		self.length += data.len() as u64;
+		let state = &mut self.state;
+		md_update(&mut self.buffer, BLOCK_SIZE, data, |block| compress(state, block));
+	}
+
+	pub fn digest(&self) -> [u8; DIGEST_SIZE] {
+		let mut h = self.clone();
+		let pad = md_padding(self.length);
+		h.update(&pad);
+		assert!(h.buffer.is_empty());
+
+		let mut digest = [0u8; DIGEST_SIZE];
+		for (i, word) in h.state.iter().enumerate() {
+			digest[4 * i..4 * i + 4].copy_from_slice(&word.to_le_bytes());
+		}
+		digest
+	}
+}
+
+pub fn md4(data: &[u8]) -> [u8; DIGEST_SIZE] {
+	let mut h = Md4::new();
+	h.update(data);
+	h.digest()
+}
+
+impl Hash for Md4 {
+	const BLOCK_SIZE: usize = BLOCK_SIZE;
+	const DIGEST_SIZE: usize = DIGEST_SIZE;
+
+	fn new() -> Md4 {
+		Md4::new()
+	}
+
+	fn update(&mut self, data: &[u8]) {
+		Md4::update(self, data)
+	}
+
+	fn digest(&self) -> Vec<u8> {
+		Md4::digest(self).to_vec()
+	}
+}
+
+impl InjectableHash for Md4 {
+	fn md_padding(len: u64) -> Vec<u8> {
+		md_padding(len)
+	}
+
+	fn from_digest(digest: &[u8], length: u64) -> Result<Md4, CryptoError> {
+		if digest.len() != DIGEST_SIZE {
+			return Err(CryptoError::DigestLength(digest.len()));
+		}
+		Md4::from_state(state_from_digest(digest), length)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use utils::cryptodata::{CryptoData, CryptoError};
+	use utils::hash::InjectableHash;
+	use utils::md4::{md4, md_padding, state_from_digest, Md4, BLOCK_SIZE};
+
+	fn md4_hex(text: &str) -> String {
+		CryptoData::from_slice(&md4(text.as_bytes())).to_hex()
+	}
+
+	// RFC 1320 A.5
+	#[test]
+	fn rfc1320_empty() {
+		assert_eq!(md4_hex(""), "31d6cfe0d16ae931b73c59d7e0c089c0");
+	}
+
+	#[test]
+	fn rfc1320_a() {
+		assert_eq!(md4_hex("a"), "bde52cb31de33e46245e05fbdbd6fb24");
+	}
+
+	#[test]
+	fn rfc1320_abc() {
+		assert_eq!(md4_hex("abc"), "a448017aaf21d8525fc10ae87aa6729d");
+	}
+
+	#[test]
+	fn rfc1320_message_digest() {
+		assert_eq!(md4_hex("message digest"), "d9130a8164549fe818874806e1c7014b");
+	}
+
+	#[test]
+	fn rfc1320_alphabet() {
+		assert_eq!(md4_hex("abcdefghijklmnopqrstuvwxyz"), "d79e1c308aa5bbcdeea8ed63df412da9");
+	}
+
+	#[test]
+	fn rfc1320_alphanumeric() {
+		assert_eq!(md4_hex("ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789"),
+			"043f8582f241db351ce627e153e7f0e4");
+	}
+
+	#[test]
+	fn rfc1320_digits() {
+		assert_eq!(md4_hex("12345678901234567890123456789012345678901234567890123456789012345678901234567890"),
+			"e33b4ddc9c38f2199c3e7b164fcc0536");
+	}
+
+	#[test]
+	fn mac_prefix() {
+		let key = CryptoData::from_text("YELLOW SUBMARINE");
+		let msg = CryptoData::from_text("comment1=cooking%20MCs;userdata=foo");
+		let mac = msg.MD4_mac_prefix(&key);
+		assert_eq!(&mac[..], &md4(&key.cat(&msg))[..]);
+		assert!(mac != msg.MD4_mac_prefix(&CryptoData::from_text("YELLOW SUBMARINF")));
+	}
+
+	#[test]
+	fn from_state_resumes() {
+		let msg: Vec<u8> = (0..200u32).map(|i| (i * 7) as u8).collect();
+
+		// from the state after the first two blocks
+		let mut h = Md4::new();
+		h.update(&msg[..2 * BLOCK_SIZE]);
+		let mut resumed = Md4::from_state(h.state(), h.length()).unwrap();
+		resumed.update(&msg[2 * BLOCK_SIZE..]);
+		assert_eq!(resumed.digest(), md4(&msg));
+
+		// from a digest, the padding becomes part of the message
+		let digest = md4(&msg[..50]);
+		let mut glued = msg[..50].to_vec();
+		glued.extend_from_slice(&md_padding(50));
+		let mut resumed = Md4::from_digest(&digest, glued.len() as u64).unwrap();
+		resumed.update(&msg[50..]);
+		glued.extend_from_slice(&msg[50..]);
+		assert_eq!(resumed.digest(), md4(&glued));
+	}
+
+	#[test]
+	fn from_state_rejects_partial_blocks() {
+		let state = state_from_digest(&md4(b"abc"));
+		assert!(match Md4::from_state(state, 50) {
+			Err(CryptoError::BlockLength(50, 64)) => true,
+			_ => false,
+		});
+		assert!(match Md4::from_digest(&[0u8; 20], 64) {
+			Err(CryptoError::DigestLength(20)) => true,
+			_ => false,
+		});
+	}
+}
@@ -1,3 +1,26 @@
+pub mod aes;
+pub mod bignum;
+pub mod blockcipher;
 pub mod cryptodata;
+pub mod ct;
+pub mod ctr;
+pub mod dh;
+pub mod gcm;
+pub mod hash;
+pub mod hmac;
+// chal29 and chal30 aren't run by main()
+#[allow(dead_code)]
+pub mod lenext;
+pub mod md4;
 pub mod mersenne;
+// not every scheme is used by the chals
+#[allow(dead_code)]
+pub mod padding;
+pub mod protocol;
+pub mod sha1;
+pub mod sha2;
+pub mod srp;
+// only used by the tests so far
+#[allow(dead_code)]
+pub mod stream;
 pub mod utils;
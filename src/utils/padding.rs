@@ -0,0 +1,234 @@
+use rand::Rng;
+
+use utils::cryptodata::CryptoError;
+
+// Block cipher padding scheme
+pub trait Padding {
+	// pad data in place to a multiple of bsize
+	fn pad(&self, data: &mut Vec<u8>, bsize: usize);
+
+	// length of data without the padding
+	fn unpadded_len(&self, data: &[u8], bsize: usize) -> Result<usize, CryptoError>;
+}
+
+// number of padding bytes, always at least one
+fn pad_size(len: usize, bsize: usize) -> usize {
+	assert!(0 < bsize && bsize < 256);
+	bsize - len % bsize
+}
+
+// check the length and return the last byte as the padding size
+fn pad_size_byte(data: &[u8], bsize: usize) -> Result<usize, CryptoError> {
+	if data.is_empty() || !data.len().is_multiple_of(bsize) {
+		return Err(CryptoError::Padding);
+	}
+	let pad_size = data[data.len() - 1] as usize;
+	if !(0 < pad_size && pad_size <= bsize) {
+		return Err(CryptoError::Padding);
+	}
+	Ok(pad_size)
+}
+
+// n bytes of value n
+pub struct Pkcs7;
+
+impl Padding for Pkcs7 {
+	fn pad(&self, data: &mut Vec<u8>, bsize: usize) {
+		let pad_size = pad_size(data.len(), bsize);
+		let len = data.len();
+		data.resize(len + pad_size, pad_size as u8);
+	}
+
+	fn unpadded_len(&self, data: &[u8], bsize: usize) -> Result<usize, CryptoError> {
+		let pad_size = pad_size_byte(data, bsize)?;
+		let len = data.len() - pad_size;
+		if data[len..].iter().any(|b| *b as usize != pad_size) {
+			return Err(CryptoError::Padding);
+		}
+		Ok(len)
+	}
+}
+
+// zeros followed by the padding size
+pub struct AnsiX923;
+
+impl Padding for AnsiX923 {
+	fn pad(&self, data: &mut Vec<u8>, bsize: usize) {
+		let pad_size = pad_size(data.len(), bsize);
+		let len = data.len();
+		data.resize(len + pad_size - 1, 0);
+		data.push(pad_size as u8);
+	}
+
+	fn unpadded_len(&self, data: &[u8], bsize: usize) -> Result<usize, CryptoError> {
+		let pad_size = pad_size_byte(data, bsize)?;
+		let len = data.len() - pad_size;
+		if data[len..data.len() - 1].iter().any(|b| *b != 0) {
+			return Err(CryptoError::Padding);
+		}
+		Ok(len)
+	}
+}
+
+// random bytes followed by the padding size
+pub struct Iso10126;
+
+impl Padding for Iso10126 {
+	fn pad(&self, data: &mut Vec<u8>, bsize: usize) {
+		let pad_size = pad_size(data.len(), bsize);
+		let mut rng = rand::rng();
+		for _ in 0..pad_size - 1 {
+			data.push(rng.random());
+		}
+		data.push(pad_size as u8);
+	}
+
+	fn unpadded_len(&self, data: &[u8], bsize: usize) -> Result<usize, CryptoError> {
+		let pad_size = pad_size_byte(data, bsize)?;
+		Ok(data.len() - pad_size)
+	}
+}
+
+// 0x80 followed by zeros (ISO/IEC 7816-4)
+pub struct Iso7816;
+
+impl Padding for Iso7816 {
+	fn pad(&self, data: &mut Vec<u8>, bsize: usize) {
+		let pad_size = pad_size(data.len(), bsize);
+		let len = data.len();
+		data.push(0x80);
+		data.resize(len + pad_size, 0);
+	}
+
+	fn unpadded_len(&self, data: &[u8], bsize: usize) -> Result<usize, CryptoError> {
+		if data.is_empty() || !data.len().is_multiple_of(bsize) {
+			return Err(CryptoError::Padding);
+		}
+		// the marker has to be within the last block
+		for (i, b) in data.iter().rev().take(bsize).enumerate() {
+			match *b {
+				0x00 => continue,
+				0x80 => return Ok(data.len() - i - 1),
+				_ => break,
+			}
+		}
+		Err(CryptoError::Padding)
+	}
+}
+
+// zeros up to the block size, nothing is added to complete blocks.
+// Not reversible: trailing zeros of the data itself are stripped too
+// (up to bsize - 1 of them), only use it for data that can't end in 0.
+pub struct ZeroPadding;
+
+impl Padding for ZeroPadding {
+	fn pad(&self, data: &mut Vec<u8>, bsize: usize) {
+		let pad_size = pad_size(data.len(), bsize) % bsize;
+		let len = data.len();
+		data.resize(len + pad_size, 0);
+	}
+
+	fn unpadded_len(&self, data: &[u8], bsize: usize) -> Result<usize, CryptoError> {
+		if !data.len().is_multiple_of(bsize) {
+			return Err(CryptoError::Padding);
+		}
+		let zeros = data.iter().rev().take(bsize - 1).take_while(|b| **b == 0).count();
+		Ok(data.len() - zeros)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use utils::cryptodata::CryptoError;
+	use utils::padding::{AnsiX923, Iso10126, Iso7816, Padding, Pkcs7, ZeroPadding};
+
+	const BSIZE: usize = 16;
+
+	// no zero bytes, so ZeroPadding can round trip it too
+	fn data(len: usize) -> Vec<u8> {
+		(1..=len as u8).collect()
+	}
+
+	fn padded<P: Padding>(padding: &P, len: usize) -> Vec<u8> {
+		let mut buf = data(len);
+		padding.pad(&mut buf, BSIZE);
+		buf
+	}
+
+	fn round_trip<P: Padding>(padding: &P, full_block: usize) {
+		for &len in [0, 15, 16].iter() {
+			let buf = padded(padding, len);
+			let expected = if len % BSIZE == 0 { len + full_block } else { BSIZE };
+			assert_eq!(buf.len(), expected);
+			assert_eq!(&buf[..len], &data(len)[..]);
+			assert_eq!(padding.unpadded_len(&buf, BSIZE).unwrap(), len);
+		}
+	}
+
+	fn rejects<P: Padding>(padding: &P, hex: &str) -> bool {
+		let buf = hex::decode(hex).unwrap();
+		matches!(padding.unpadded_len(&buf, BSIZE), Err(CryptoError::Padding))
+	}
+
+	#[test]
+	fn round_trips() {
+		round_trip(&Pkcs7, BSIZE);
+		round_trip(&AnsiX923, BSIZE);
+		round_trip(&Iso10126, BSIZE);
+		round_trip(&Iso7816, BSIZE);
+		round_trip(&ZeroPadding, 0);
+	}
+
+	#[test]
+	fn padding_bytes() {
+		assert_eq!(hex::encode(padded(&Pkcs7, 13)), "0102030405060708090a0b0c0d030303");
+		assert_eq!(hex::encode(padded(&AnsiX923, 13)), "0102030405060708090a0b0c0d000003");
+		assert_eq!(hex::encode(padded(&Iso7816, 13)), "0102030405060708090a0b0c0d800000");
+		assert_eq!(hex::encode(padded(&ZeroPadding, 13)), "0102030405060708090a0b0c0d000000");
+		assert_eq!(padded(&Iso10126, 13)[15], 3);
+	}
+
+	#[test]
+	fn bad_pad_size() {
+		for &hex in ["0102030405060708090a0b0c0d0e0f00", "0102030405060708090a0b0c0d0e0f11",
+			"0102030405060708090a0b0c0d0e0fff"].iter() {
+			assert!(rejects(&Pkcs7, hex));
+			assert!(rejects(&AnsiX923, hex));
+			assert!(rejects(&Iso10126, hex));
+		}
+		// not a whole number of blocks
+		assert!(rejects(&Pkcs7, ""));
+		assert!(rejects(&Pkcs7, "0102030405060708090a0b0c0d0e01"));
+		assert!(rejects(&Pkcs7, "0102030405060708090a0b0c0d020303"));
+	}
+
+	#[test]
+	fn bad_ansi_x923_zeros() {
+		assert!(rejects(&AnsiX923, "0102030405060708090a0b0c0d010003"));
+		assert!(rejects(&AnsiX923, "0102030405060708090a0b0c0d000103"));
+		// same bytes are fine for ISO 10126
+		assert_eq!(Iso10126.unpadded_len(&hex::decode("0102030405060708090a0b0c0d010203").unwrap(), BSIZE).unwrap(), 13);
+	}
+
+	#[test]
+	fn missing_iso7816_marker() {
+		assert!(rejects(&Iso7816, "0102030405060708090a0b0c0d000000"));
+		assert!(rejects(&Iso7816, "0102030405060708090a0b0c0d800001"));
+		assert!(rejects(&Iso7816, "00000000000000000000000000000000"));
+		// a marker in the block before doesn't count
+		assert!(rejects(&Iso7816, "0102030405060708090a0b0c0d0e0f80\
+			00000000000000000000000000000000"));
+	}
+
+	#[test]
+	fn zero_padding_is_ambiguous() {
+		// data ending in zeros loses them
+		let mut buf = vec![1, 2, 0, 0];
+		ZeroPadding.pad(&mut buf, BSIZE);
+		assert_eq!(ZeroPadding.unpadded_len(&buf, BSIZE).unwrap(), 2);
+
+		// a block of zeros keeps one of them
+		assert_eq!(ZeroPadding.unpadded_len(&[0u8; 16], BSIZE).unwrap(), 1);
+		assert!(rejects(&ZeroPadding, "0102"));
+	}
+}
@@ -0,0 +1,376 @@
+// In-process message passing between two parties, optionally with
+// a man in the middle relaying (and tampering with) everything they send.
+// Every party runs in its own thread and talks through an Endpoint.
+
+use std::error;
+use std::fmt;
+use std::io;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use utils::cryptodata::CryptoError;
+
+#[derive (Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Side {
+	Alice,
+	Bob,
+}
+
+impl Side {
+	pub fn other(self) -> Side {
+		match self {
+			Side::Alice => Side::Bob,
+			Side::Bob => Side::Alice,
+		}
+	}
+}
+
+#[derive (Debug)]
+pub enum ProtocolError {
+	// the other side (or the relay) has gone away
+	Disconnected,
+	// a message that doesn't fit the protocol state
+	Unexpected(&'static str),
+	Crypto(CryptoError),
+	Io(io::Error),
+}
+
+impl fmt::Display for ProtocolError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			ProtocolError::Disconnected => write!(f, "peer disconnected"),
+			ProtocolError::Unexpected(what) => write!(f, "unexpected message, expected {}", what),
+			ProtocolError::Crypto(ref e) => write!(f, "{}", e),
+			ProtocolError::Io(ref e) => write!(f, "I/O error: {}", e),
+		}
+	}
+}
+
+impl error::Error for ProtocolError {}
+
+impl From<CryptoError> for ProtocolError {
+	fn from(e: CryptoError) -> ProtocolError {
+		ProtocolError::Crypto(e)
+	}
+}
+
+impl From<io::Error> for ProtocolError {
+	fn from(e: io::Error) -> ProtocolError {
+		ProtocolError::Io(e)
+	}
+}
+
+// Anything a party can exchange messages over,
+// so the same protocol code runs in-process and over a socket
+pub trait Transport<T> {
+	fn send(&mut self, msg: T) -> Result<(), ProtocolError>;
+	fn recv(&mut self) -> Result<T, ProtocolError>;
+}
+
+// Messages that can go over a text connection, one per line
+pub trait WireFormat: Sized {
+	fn to_line(&self) -> String;
+	fn from_line(line: &str) -> Result<Self, ProtocolError>;
+}
+
+// what actually goes over the channels
+enum Packet<T> {
+	Message(Side, T),
+	// an endpoint was dropped
+	Hangup(Side),
+}
+
+// one party's end of the link
+pub struct Endpoint<T> {
+	side: Side,
+	tx: Sender<Packet<T>>,
+	rx: Receiver<Packet<T>>,
+}
+
+impl<T> Endpoint<T> {
+	pub fn side(&self) -> Side {
+		self.side
+	}
+
+	pub fn send(&self, msg: T) -> Result<(), ProtocolError> {
+		self.tx.send(Packet::Message(self.side, msg)).map_err(|_| ProtocolError::Disconnected)
+	}
+
+	pub fn recv(&self) -> Result<T, ProtocolError> {
+		match self.rx.recv() {
+			Ok(Packet::Message(_, msg)) => Ok(msg),
+			_ => Err(ProtocolError::Disconnected),
+		}
+	}
+}
+
+impl<T> Transport<T> for Endpoint<T> {
+	fn send(&mut self, msg: T) -> Result<(), ProtocolError> {
+		Endpoint::send(self, msg)
+	}
+
+	fn recv(&mut self) -> Result<T, ProtocolError> {
+		Endpoint::recv(self)
+	}
+}
+
+// let the peer know, so it doesn't wait forever for an answer
+impl<T> Drop for Endpoint<T> {
+	fn drop(&mut self) {
+		let _ = self.tx.send(Packet::Hangup(self.side));
+	}
+}
+
+// Alice and Bob talking directly
+pub fn link<T>() -> (Endpoint<T>, Endpoint<T>) {
+	let (to_bob, bob_rx) = channel();
+	let (to_alice, alice_rx) = channel();
+	let alice = Endpoint { side: Side::Alice, tx: to_bob, rx: alice_rx };
+	let bob = Endpoint { side: Side::Bob, tx: to_alice, rx: bob_rx };
+	(alice, bob)
+}
+
+// Alice and Bob, both unknowingly talking to Mallory
+pub fn mitm_link<T>() -> (Endpoint<T>, Endpoint<T>, Mallory<T>) {
+	let (to_mallory, inbox) = channel();
+	let (to_alice, alice_rx) = channel();
+	let (to_bob, bob_rx) = channel();
+	let alice = Endpoint { side: Side::Alice, tx: to_mallory.clone(), rx: alice_rx };
+	let bob = Endpoint { side: Side::Bob, tx: to_mallory, rx: bob_rx };
+	let mallory = Mallory { inbox, to_alice: Some(to_alice), to_bob: Some(to_bob) };
+	(alice, bob, mallory)
+}
+
+pub struct Mallory<T> {
+	inbox: Receiver<Packet<T>>,
+	// dropped when the other party hangs up
+	to_alice: Option<Sender<Packet<T>>>,
+	to_bob: Option<Sender<Packet<T>>>,
+}
+
+impl<T> Mallory<T> {
+	fn sender(&mut self, to: Side) -> &mut Option<Sender<Packet<T>>> {
+		match to {
+			Side::Alice => &mut self.to_alice,
+			Side::Bob => &mut self.to_bob,
+		}
+	}
+
+	// next message of either party with its sender,
+	// Disconnected once both have hung up
+	pub fn recv(&mut self) -> Result<(Side, T), ProtocolError> {
+		loop {
+			match self.inbox.recv() {
+				Ok(Packet::Message(from, msg)) => return Ok((from, msg)),
+				// pass the hangup on
+				Ok(Packet::Hangup(from)) => *self.sender(from.other()) = None,
+				Err(_) => return Err(ProtocolError::Disconnected),
+			}
+		}
+	}
+
+	// deliver msg to a party, it appears to come from the other one
+	pub fn send(&mut self, to: Side, msg: T) -> Result<(), ProtocolError> {
+		match *self.sender(to) {
+			Some(ref tx) => tx.send(Packet::Message(to.other(), msg)).map_err(|_| ProtocolError::Disconnected),
+			None => Err(ProtocolError::Disconnected),
+		}
+	}
+
+	// Relay until both parties hang up. Every message goes through
+	// intercept(from, msg), which returns the message to deliver
+	// to the other side (possibly rewritten) or None to drop it.
+	pub fn relay<F: FnMut(Side, T) -> Option<T>>(&mut self, mut intercept: F) {
+		while let Ok((from, msg)) = self.recv() {
+			if let Some(msg) = intercept(from, msg) {
+				// the recipient may have finished already
+				let _ = self.send(from.other(), msg);
+			}
+		}
+	}
+}
+
+// newline separated messages over TCP
+pub struct TcpTransport {
+	reader: BufReader<TcpStream>,
+	writer: TcpStream,
+}
+
+impl TcpTransport {
+	pub fn new(stream: TcpStream) -> Result<TcpTransport, ProtocolError> {
+		let writer = stream.try_clone()?;
+		Ok(TcpTransport { reader: BufReader::new(stream), writer })
+	}
+}
+
+impl<T: WireFormat> Transport<T> for TcpTransport {
+	fn send(&mut self, msg: T) -> Result<(), ProtocolError> {
+		let line = msg.to_line();
+		// the peer would read it as several messages
+		if line.contains('\n') {
+			return Err(ProtocolError::Io(io::Error::new(io::ErrorKind::InvalidInput, "message contains a newline")));
+		}
+		writeln!(self.writer, "{}", line)?;
+		Ok(())
+	}
+
+	fn recv(&mut self) -> Result<T, ProtocolError> {
+		let mut line = String::new();
+		if self.reader.read_line(&mut line)? == 0 {
+			return Err(ProtocolError::Disconnected);
+		}
+		T::from_line(line.trim_end())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::net::{TcpListener, TcpStream};
+	use std::thread;
+
+	use utils::protocol::{link, mitm_link, ProtocolError, Side, TcpTransport, Transport, WireFormat};
+
+	fn disconnected<T>(res: Result<T, ProtocolError>) -> bool {
+		matches!(res, Err(ProtocolError::Disconnected))
+	}
+
+	#[test]
+	fn direct_link() {
+		let (alice, bob) = link();
+		let bob = thread::spawn(move || {
+			let n: u32 = bob.recv().unwrap();
+			bob.send(n + 1).unwrap();
+		});
+		alice.send(41).unwrap();
+		assert_eq!(alice.recv().unwrap(), 42);
+		bob.join().unwrap();
+		// Bob's endpoint is gone
+		assert!(disconnected(alice.recv()));
+	}
+
+	#[test]
+	fn mallory_observes() {
+		let (alice, bob, mut mallory) = mitm_link();
+		let mallory = thread::spawn(move || {
+			let mut seen = Vec::new();
+			mallory.relay(|from, msg: String| {
+				seen.push((from, msg.clone()));
+				Some(msg)
+			});
+			seen
+		});
+		let bob = thread::spawn(move || {
+			let msg = bob.recv().unwrap();
+			bob.send(format!("re: {}", msg)).unwrap();
+		});
+
+		alice.send("hello".to_string()).unwrap();
+		assert_eq!(alice.recv().unwrap(), "re: hello");
+		bob.join().unwrap();
+		drop(alice);
+
+		assert_eq!(mallory.join().unwrap(), vec![
+			(Side::Alice, "hello".to_string()),
+			(Side::Bob, "re: hello".to_string()),
+		]);
+	}
+
+	#[test]
+	fn mallory_drops() {
+		let (alice, bob, mut mallory) = mitm_link();
+		let mallory = thread::spawn(move || {
+			mallory.relay(|_, msg: u32| if msg % 2 == 0 { Some(msg) } else { None });
+		});
+		let bob = thread::spawn(move || {
+			let mut received = Vec::new();
+			while let Ok(n) = bob.recv() {
+				received.push(n);
+			}
+			received
+		});
+
+		for n in 0..6 {
+			alice.send(n).unwrap();
+		}
+		// Bob stops waiting once Alice hangs up
+		drop(alice);
+		assert_eq!(bob.join().unwrap(), vec![0, 2, 4]);
+		mallory.join().unwrap();
+	}
+
+	#[test]
+	fn mallory_rewrites() {
+		let (alice, bob, mut mallory) = mitm_link();
+		let mallory = thread::spawn(move || {
+			mallory.relay(|from, msg: String| match from {
+				Side::Alice => Some(msg.replace("user", "admin")),
+				Side::Bob => Some(msg),
+			});
+		});
+		let bob = thread::spawn(move || {
+			let msg = bob.recv().unwrap();
+			bob.send(msg.clone()).unwrap();
+			msg
+		});
+
+		alice.send("role=user".to_string()).unwrap();
+		assert_eq!(alice.recv().unwrap(), "role=admin");
+		assert_eq!(bob.join().unwrap(), "role=admin");
+		drop(alice);
+		mallory.join().unwrap();
+	}
+
+	#[test]
+	fn mallory_impersonates() {
+		let (alice, bob, mut mallory) = mitm_link();
+		alice.send(1u32).unwrap();
+		assert_eq!(mallory.recv().unwrap(), (Side::Alice, 1));
+
+		// Bob gets a message that looks like it's from Alice
+		mallory.send(Side::Bob, 2).unwrap();
+		assert_eq!(bob.recv().unwrap(), 2);
+
+		// once both have hung up there's nobody left to talk to
+		drop(alice);
+		drop(bob);
+		assert!(disconnected(mallory.recv()));
+		assert!(disconnected(mallory.send(Side::Bob, 3)));
+		assert!(disconnected(mallory.send(Side::Alice, 3)));
+	}
+
+	struct Line(String);
+
+	impl WireFormat for Line {
+		fn to_line(&self) -> String {
+			self.0.clone()
+		}
+
+		fn from_line(line: &str) -> Result<Line, ProtocolError> {
+			Ok(Line(line.to_string()))
+		}
+	}
+
+	#[test]
+	fn tcp_transport() {
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let addr = listener.local_addr().unwrap();
+		let server = thread::spawn(move || {
+			let mut peer = TcpTransport::new(listener.accept().unwrap().0).unwrap();
+			let line: Line = peer.recv().unwrap();
+			peer.send(Line(line.0.to_uppercase())).unwrap();
+		});
+
+		let mut client = TcpTransport::new(TcpStream::connect(addr).unwrap()).unwrap();
+		// refused instead of arriving as two messages
+		match client.send(Line("one\ntwo".to_string())) {
+			Err(ProtocolError::Io(_)) => (),
+			_ => panic!("multi-line message sent"),
+		}
+		client.send(Line("one two".to_string())).unwrap();
+		let reply: Line = client.recv().unwrap();
+		assert_eq!(reply.0, "ONE TWO");
+		server.join().unwrap();
+		assert!(disconnected(Transport::<Line>::recv(&mut client)));
+	}
+}
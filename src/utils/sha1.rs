@@ -0,0 +1,225 @@
+// Native SHA-1 with access to the internal chaining state,
+// so it can be resumed from a known digest (length extension)
+
+use utils::cryptodata::CryptoError;
+use utils::hash::{md_update, Hash, InjectableHash};
+
+pub const BLOCK_SIZE: usize = 64;
+pub const DIGEST_SIZE: usize = 20;
+
+const INIT_STATE: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+
+#[derive (Clone)]
+pub struct Sha1 {
+	state: [u32; 5],
+	// unprocessed data, always shorter than a block
+	buffer: Vec<u8>,
+	// number of bytes hashed so far, including the buffer
+	length: u64,
+}
+
+// Merkle-Damgård padding for a message of len bytes:
+// 0x80, zeros and the big-endian bit length
+pub fn md_padding(len: u64) -> Vec<u8> {
+	let mut pad = vec![0x80u8];
+	let zeros = (BLOCK_SIZE + 55 - (len as usize % BLOCK_SIZE)) % BLOCK_SIZE;
+	pad.resize(1 + zeros, 0);
+	pad.extend_from_slice(&(len.wrapping_mul(8)).to_be_bytes());
+	pad
+}
+
+// split a digest back into the chaining state
+pub fn state_from_digest(digest: &[u8]) -> [u32; 5] {
+	assert!(digest.len() == DIGEST_SIZE);
+	let mut state = [0u32; 5];
+	for (i, word) in digest.chunks(4).enumerate() {
+		state[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+	}
+	state
+}
+
+pub fn compress(state: &mut [u32; 5], block: &[u8]) {
+	assert!(block.len() == BLOCK_SIZE);
+	let mut w = [0u32; 80];
+	for (i, word) in block.chunks(4).enumerate() {
+		w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+	}
+	for i in 16..80 {
+		w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+	}
+
+	let [mut a, mut b, mut c, mut d, mut e] = *state;
+	for (i, wi) in w.iter().enumerate() {
+		let (f, k) = match i {
+			0..=19 => ((b & c) | (!b & d), 0x5a827999),
+			20..=39 => (b ^ c ^ d, 0x6ed9eba1),
+			40..=59 => ((b & c) | (b & d) | (c & d), 0x8f1bbcdc),
+			_ => (b ^ c ^ d, 0xca62c1d6),
+		};
+		let temp = a.rotate_left(5).wrapping_add(f).wrapping_add(e)
+			.wrapping_add(k).wrapping_add(*wi);
+		e = d;
+		d = c;
+		c = b.rotate_left(30);
+		b = a;
+		a = temp;
+	}
+
+	state[0] = state[0].wrapping_add(a);
+	state[1] = state[1].wrapping_add(b);
+	state[2] = state[2].wrapping_add(c);
+	state[3] = state[3].wrapping_add(d);
+	state[4] = state[4].wrapping_add(e);
+}
+
+impl Sha1 {
+	pub fn new() -> Sha1 {
+		Sha1 { state: INIT_STATE, buffer: Vec::with_capacity(BLOCK_SIZE), length: 0 }
+	}
+
+	pub fn from_state(state: [u32; 5], length: u64) -> Result<Sha1, CryptoError> {
+		if !length.is_multiple_of(BLOCK_SIZE as u64) {
+			return Err(CryptoError::BlockLength(length as usize, BLOCK_SIZE));
+		}
+		Ok(Sha1 { state, buffer: Vec::with_capacity(BLOCK_SIZE), length })
+	}
+
+	pub fn state(&self) -> [u32; 5] {
+		self.state
+	}
+
+	pub fn length(&self) -> u64 {
+		self.length
+	}
+
+	pub fn update(&mut self, data: &[u8]) {
+		self.length += data.len() as u64;
+		let state = &mut self.state;
+		md_update(&mut self.buffer, BLOCK_SIZE, data, |block| compress(state, block));
+	}
+
+	pub fn digest(&self) -> [u8; DIGEST_SIZE] {
+		let mut h = self.clone();
+		let pad = md_padding(self.length);
+		h.update(&pad);
+		assert!(h.buffer.is_empty());
+
+		let mut digest = [0u8; DIGEST_SIZE];
+		for (i, word) in h.state.iter().enumerate() {
+			digest[4 * i..4 * i + 4].copy_from_slice(&word.to_be_bytes());
+		}
+		digest
+	}
+}
+
+pub fn sha1(data: &[u8]) -> [u8; DIGEST_SIZE] {
+	let mut h = Sha1::new();
+	h.update(data);
+	h.digest()
+}
+
+impl Hash for Sha1 {
+	const BLOCK_SIZE: usize = BLOCK_SIZE;
+	const DIGEST_SIZE: usize = DIGEST_SIZE;
+
+	fn new() -> Sha1 {
+		Sha1::new()
+	}
+
+	fn update(&mut self, data: &[u8]) {
+		Sha1::update(self, data)
+	}
+
+	fn digest(&self) -> Vec<u8> {
+		Sha1::digest(self).to_vec()
+	}
+}
+
+impl InjectableHash for Sha1 {
+	fn md_padding(len: u64) -> Vec<u8> {
+		md_padding(len)
+	}
+
+	fn from_digest(digest: &[u8], length: u64) -> Result<Sha1, CryptoError> {
+		if digest.len() != DIGEST_SIZE {
+			return Err(CryptoError::DigestLength(digest.len()));
+		}
+		Sha1::from_state(state_from_digest(digest), length)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use utils::cryptodata::{CryptoData, CryptoError};
+	use utils::hash::InjectableHash;
+	use utils::sha1::{md_padding, sha1, state_from_digest, Sha1, BLOCK_SIZE};
+
+	fn sha1_hex(data: &[u8]) -> String {
+		CryptoData::from_slice(&sha1(data)).to_hex()
+	}
+
+	// FIPS 180-2 appendix A
+	#[test]
+	fn fips180_abc() {
+		assert_eq!(sha1_hex(b"abc"), "a9993e364706816aba3e25717850c26c9cd0d89d");
+	}
+
+	#[test]
+	fn fips180_two_blocks() {
+		assert_eq!(sha1_hex(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+			"84983e441c3bd26ebaae4aa1f95129e5e54670f1");
+	}
+
+	#[test]
+	fn fips180_million_a() {
+		// fed in uneven pieces to go through the buffering
+		let mut h = Sha1::new();
+		for _ in 0..1000 {
+			h.update(&[b'a'; 999]);
+		}
+		h.update(&[b'a'; 1000]);
+		assert_eq!(CryptoData::from_slice(&h.digest()).to_hex(), "34aa973cd4c4daa4f61eeb2bdbad27316534016f");
+	}
+
+	#[test]
+	fn empty() {
+		assert_eq!(sha1_hex(b""), "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+	}
+
+	#[test]
+	fn mac_prefix() {
+		let key = CryptoData::from_text("YELLOW SUBMARINE");
+		let msg = CryptoData::from_text("comment1=cooking%20MCs;userdata=foo");
+		let mac = msg.SHA1_mac_prefix(&key);
+		assert_eq!(&mac[..], &sha1(&key.cat(&msg))[..]);
+		assert!(mac != msg.SHA1_mac_prefix(&CryptoData::from_text("YELLOW SUBMARINF")));
+	}
+
+	#[test]
+	fn from_state_resumes() {
+		let msg: Vec<u8> = (0..200u32).map(|i| (i * 7) as u8).collect();
+
+		// from the state after the first two blocks
+		let mut h = Sha1::new();
+		h.update(&msg[..2 * BLOCK_SIZE]);
+		let mut resumed = Sha1::from_state(h.state(), h.length()).unwrap();
+		resumed.update(&msg[2 * BLOCK_SIZE..]);
+		assert_eq!(resumed.digest(), sha1(&msg));
+
+		// from a digest, the padding becomes part of the message
+		let digest = sha1(&msg[..50]);
+		let mut glued = msg[..50].to_vec();
+		glued.extend_from_slice(&md_padding(50));
+		let mut resumed = Sha1::from_digest(&digest, glued.len() as u64).unwrap();
+		resumed.update(&msg[50..]);
+		glued.extend_from_slice(&msg[50..]);
+		assert_eq!(resumed.digest(), sha1(&glued));
+	}
+
+	#[test]
+	fn from_state_rejects_partial_blocks() {
+		let state = state_from_digest(&sha1(b"abc"));
+		assert!(matches!(Sha1::from_state(state, 50), Err(CryptoError::BlockLength(50, 64))));
+		assert!(matches!(Sha1::from_digest(&[0u8; 16], 64), Err(CryptoError::DigestLength(16))));
+	}
+}
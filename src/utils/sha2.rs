@@ -0,0 +1,600 @@
+// Native SHA-2 family (FIPS 180-4) with access to the internal chaining state.
+// SHA-224 and SHA-384 are truncated variants of SHA-256 and SHA-512
+// with their own initial values.
+
+use utils::cryptodata::CryptoError;
+use utils::hash::{md_update, Hash, InjectableHash};
+
+pub const SHA256_BLOCK_SIZE: usize = 64;
+pub const SHA512_BLOCK_SIZE: usize = 128;
+
+const SHA224_INIT: [u32; 8] = [
+	0xc1059ed8, 0x367cd507, 0x3070dd17, 0xf70e5939, 0xffc00b31, 0x68581511, 0x64f98fa7, 0xbefa4fa4,
+];
+
+const SHA256_INIT: [u32; 8] = [
+	0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+const SHA384_INIT: [u64; 8] = [
+	0xcbbb9d5dc1059ed8, 0x629a292a367cd507, 0x9159015a3070dd17, 0x152fecd8f70e5939,
+	0x67332667ffc00b31, 0x8eb44a8768581511, 0xdb0c2e0d64f98fa7, 0x47b5481dbefa4fa4,
+];
+
+const SHA512_INIT: [u64; 8] = [
+	0x6a09e667f3bcc908, 0xbb67ae8584caa73b, 0x3c6ef372fe94f82b, 0xa54ff53a5f1d36f1,
+	0x510e527fade682d1, 0x9b05688c2b3e6c1f, 0x1f83d9abfb41bd6b, 0x5be0cd19137e2179,
+];
+
+const K256: [u32; 64] = [
+	0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+	0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+	0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+	0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+	0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+	0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+	0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+	0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+const K512: [u64; 80] = [
+	0x428a2f98d728ae22, 0x7137449123ef65cd, 0xb5c0fbcfec4d3b2f, 0xe9b5dba58189dbbc,
+	0x3956c25bf348b538, 0x59f111f1b605d019, 0x923f82a4af194f9b, 0xab1c5ed5da6d8118,
+	0xd807aa98a3030242, 0x12835b0145706fbe, 0x243185be4ee4b28c, 0x550c7dc3d5ffb4e2,
+	0x72be5d74f27b896f, 0x80deb1fe3b1696b1, 0x9bdc06a725c71235, 0xc19bf174cf692694,
+	0xe49b69c19ef14ad2, 0xefbe4786384f25e3, 0x0fc19dc68b8cd5b5, 0x240ca1cc77ac9c65,
+	0x2de92c6f592b0275, 0x4a7484aa6ea6e483, 0x5cb0a9dcbd41fbd4, 0x76f988da831153b5,
+	0x983e5152ee66dfab, 0xa831c66d2db43210, 0xb00327c898fb213f, 0xbf597fc7beef0ee4,
+	0xc6e00bf33da88fc2, 0xd5a79147930aa725, 0x06ca6351e003826f, 0x142929670a0e6e70,
+	0x27b70a8546d22ffc, 0x2e1b21385c26c926, 0x4d2c6dfc5ac42aed, 0x53380d139d95b3df,
+	0x650a73548baf63de, 0x766a0abb3c77b2a8, 0x81c2c92e47edaee6, 0x92722c851482353b,
+	0xa2bfe8a14cf10364, 0xa81a664bbc423001, 0xc24b8b70d0f89791, 0xc76c51a30654be30,
+	0xd192e819d6ef5218, 0xd69906245565a910, 0xf40e35855771202a, 0x106aa07032bbd1b8,
+	0x19a4c116b8d2d0c8, 0x1e376c085141ab53, 0x2748774cdf8eeb99, 0x34b0bcb5e19b48a8,
+	0x391c0cb3c5c95a63, 0x4ed8aa4ae3418acb, 0x5b9cca4f7763e373, 0x682e6ff3d6b2b8a3,
+	0x748f82ee5defb2fc, 0x78a5636f43172f60, 0x84c87814a1f0ab72, 0x8cc702081a6439ec,
+	0x90befffa23631e28, 0xa4506cebde82bde9, 0xbef9a3f7b2c67915, 0xc67178f2e372532b,
+	0xca273eceea26619c, 0xd186b8c721c0c207, 0xeada7dd6cde0eb1e, 0xf57d4f7fee6ed178,
+	0x06f067aa72176fba, 0x0a637dc5a2c898a6, 0x113f9804bef90dae, 0x1b710b35131c471b,
+	0x28db77f523047d84, 0x32caab7b40c72493, 0x3c9ebe0a15c9bebc, 0x431d67c49c100d4c,
+	0x4cc5d4becb3e42b6, 0x597f299cfc657e2a, 0x5fcb6fab3ad6faec, 0x6c44198c4a475817,
+];
+
+// Merkle-Damgård padding of SHA-224/256 for a message of len bytes:
+// 0x80, zeros and the 64-bit big-endian bit length
+pub fn sha256_padding(len: u64) -> Vec<u8> {
+	let mut pad = vec![0x80u8];
+	let zeros = (SHA256_BLOCK_SIZE + 55 - (len as usize % SHA256_BLOCK_SIZE)) % SHA256_BLOCK_SIZE;
+	pad.resize(1 + zeros, 0);
+	pad.extend_from_slice(&(len.wrapping_mul(8)).to_be_bytes());
+	pad
+}
+
+// Padding of SHA-384/512, the bit length is 128 bits long
+pub fn sha512_padding(len: u64) -> Vec<u8> {
+	let mut pad = vec![0x80u8];
+	let zeros = (SHA512_BLOCK_SIZE + 111 - (len as usize % SHA512_BLOCK_SIZE)) % SHA512_BLOCK_SIZE;
+	pad.resize(1 + zeros, 0);
+	pad.extend_from_slice(&(len as u128 * 8).to_be_bytes());
+	pad
+}
+
+pub fn sha256_compress(state: &mut [u32; 8], block: &[u8]) {
+	assert!(block.len() == SHA256_BLOCK_SIZE);
+	let mut w = [0u32; 64];
+	for (i, word) in block.chunks(4).enumerate() {
+		w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+	}
+	for i in 16..64 {
+		let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+		let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+		w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+	}
+
+	let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+	for i in 0..64 {
+		let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+		let ch = (e & f) ^ (!e & g);
+		let t1 = h.wrapping_add(s1).wrapping_add(ch).wrapping_add(K256[i]).wrapping_add(w[i]);
+		let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+		let maj = (a & b) ^ (a & c) ^ (b & c);
+		let t2 = s0.wrapping_add(maj);
+		h = g;
+		g = f;
+		f = e;
+		e = d.wrapping_add(t1);
+		d = c;
+		c = b;
+		b = a;
+		a = t1.wrapping_add(t2);
+	}
+
+	for (s, v) in state.iter_mut().zip([a, b, c, d, e, f, g, h].iter()) {
+		*s = s.wrapping_add(*v);
+	}
+}
+
+pub fn sha512_compress(state: &mut [u64; 8], block: &[u8]) {
+	assert!(block.len() == SHA512_BLOCK_SIZE);
+	let mut w = [0u64; 80];
+	for (i, word) in block.chunks(8).enumerate() {
+		let mut bytes = [0u8; 8];
+		bytes.copy_from_slice(word);
+		w[i] = u64::from_be_bytes(bytes);
+	}
+	for i in 16..80 {
+		let s0 = w[i - 15].rotate_right(1) ^ w[i - 15].rotate_right(8) ^ (w[i - 15] >> 7);
+		let s1 = w[i - 2].rotate_right(19) ^ w[i - 2].rotate_right(61) ^ (w[i - 2] >> 6);
+		w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+	}
+
+	let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+	for i in 0..80 {
+		let s1 = e.rotate_right(14) ^ e.rotate_right(18) ^ e.rotate_right(41);
+		let ch = (e & f) ^ (!e & g);
+		let t1 = h.wrapping_add(s1).wrapping_add(ch).wrapping_add(K512[i]).wrapping_add(w[i]);
+		let s0 = a.rotate_right(28) ^ a.rotate_right(34) ^ a.rotate_right(39);
+		let maj = (a & b) ^ (a & c) ^ (b & c);
+		let t2 = s0.wrapping_add(maj);
+		h = g;
+		g = f;
+		f = e;
+		e = d.wrapping_add(t1);
+		d = c;
+		c = b;
+		b = a;
+		a = t1.wrapping_add(t2);
+	}
+
+	for (s, v) in state.iter_mut().zip([a, b, c, d, e, f, g, h].iter()) {
+		*s = s.wrapping_add(*v);
+	}
+}
+
+// split a (full length) digest back into the chaining state
+pub fn sha256_state_from_digest(digest: &[u8]) -> [u32; 8] {
+	assert!(digest.len() == 32);
+	let mut state = [0u32; 8];
+	for (i, word) in digest.chunks(4).enumerate() {
+		state[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+	}
+	state
+}
+
+pub fn sha512_state_from_digest(digest: &[u8]) -> [u64; 8] {
+	assert!(digest.len() == 64);
+	let mut state = [0u64; 8];
+	for (i, word) in digest.chunks(8).enumerate() {
+		let mut bytes = [0u8; 8];
+		bytes.copy_from_slice(word);
+		state[i] = u64::from_be_bytes(bytes);
+	}
+	state
+}
+
+// Shared engine of SHA-224 and SHA-256
+#[derive (Clone)]
+struct Engine256 {
+	state: [u32; 8],
+	// unprocessed data, always shorter than a block
+	buffer: Vec<u8>,
+	// number of bytes hashed so far, including the buffer
+	length: u64,
+}
+
+impl Engine256 {
+	fn new(state: [u32; 8]) -> Engine256 {
+		Engine256 { state, buffer: Vec::with_capacity(SHA256_BLOCK_SIZE), length: 0 }
+	}
+
+	fn from_state(state: [u32; 8], length: u64) -> Result<Engine256, CryptoError> {
+		if length % SHA256_BLOCK_SIZE as u64 != 0 {
+			return Err(CryptoError::BlockLength(length as usize, SHA256_BLOCK_SIZE));
+		}
+		Ok(Engine256 { state, buffer: Vec::with_capacity(SHA256_BLOCK_SIZE), length })
+	}
+
+	fn update(&mut self, data: &[u8]) {
+		self.length += data.len() as u64;
+		let state = &mut self.state;
+		md_update(&mut self.buffer, SHA256_BLOCK_SIZE, data, |block| sha256_compress(state, block));
+	}
+
+	// full 32 byte output, truncated by SHA-224
+	fn finish(&self) -> [u8; 32] {
+		let mut h = self.clone();
+		let pad = sha256_padding(self.length);
+		h.update(&pad);
+		assert!(h.buffer.is_empty());
+
+		let mut digest = [0u8; 32];
+		for (i, word) in h.state.iter().enumerate() {
+			digest[4 * i..4 * i + 4].copy_from_slice(&word.to_be_bytes());
+		}
+		digest
+	}
+}
+
+// Shared engine of SHA-384 and SHA-512
+#[derive (Clone)]
+struct Engine512 {
+	state: [u64; 8],
+	buffer: Vec<u8>,
+	length: u64,
+}
+
+impl Engine512 {
+	fn new(state: [u64; 8]) -> Engine512 {
+		Engine512 { state, buffer: Vec::with_capacity(SHA512_BLOCK_SIZE), length: 0 }
+	}
+
+	fn from_state(state: [u64; 8], length: u64) -> Result<Engine512, CryptoError> {
+		if length % SHA512_BLOCK_SIZE as u64 != 0 {
+			return Err(CryptoError::BlockLength(length as usize, SHA512_BLOCK_SIZE));
+		}
+		Ok(Engine512 { state, buffer: Vec::with_capacity(SHA512_BLOCK_SIZE), length })
+	}
+
+	fn update(&mut self, data: &[u8]) {
+		self.length += data.len() as u64;
+		let state = &mut self.state;
+		md_update(&mut self.buffer, SHA512_BLOCK_SIZE, data, |block| sha512_compress(state, block));
+	}
+
+	// full 64 byte output, truncated by SHA-384
+	fn finish(&self) -> [u8; 64] {
+		let mut h = self.clone();
+		let pad = sha512_padding(self.length);
+		h.update(&pad);
+		assert!(h.buffer.is_empty());
+
+		let mut digest = [0u8; 64];
+		for (i, word) in h.state.iter().enumerate() {
+			digest[8 * i..8 * i + 8].copy_from_slice(&word.to_be_bytes());
+		}
+		digest
+	}
+}
+
+#[derive (Clone)]
+pub struct Sha224(Engine256);
+
+#[derive (Clone)]
+pub struct Sha256(Engine256);
+
+#[derive (Clone)]
+pub struct Sha384(Engine512);
+
+#[derive (Clone)]
+pub struct Sha512(Engine512);
+
+impl Sha224 {
+	pub fn new() -> Sha224 {
+		Sha224(Engine256::new(SHA224_INIT))
+	}
+
+	pub fn from_state(state: [u32; 8], length: u64) -> Result<Sha224, CryptoError> {
+		Ok(Sha224(Engine256::from_state(state, length)?))
+	}
+
+	pub fn state(&self) -> [u32; 8] {
+		self.0.state
+	}
+
+	pub fn length(&self) -> u64 {
+		self.0.length
+	}
+
+	pub fn update(&mut self, data: &[u8]) {
+		self.0.update(data)
+	}
+
+	pub fn digest(&self) -> [u8; 28] {
+		let mut digest = [0u8; 28];
+		digest.copy_from_slice(&self.0.finish()[..28]);
+		digest
+	}
+}
+
+impl Sha256 {
+	pub fn new() -> Sha256 {
+		Sha256(Engine256::new(SHA256_INIT))
+	}
+
+	pub fn from_state(state: [u32; 8], length: u64) -> Result<Sha256, CryptoError> {
+		Ok(Sha256(Engine256::from_state(state, length)?))
+	}
+
+	pub fn state(&self) -> [u32; 8] {
+		self.0.state
+	}
+
+	pub fn length(&self) -> u64 {
+		self.0.length
+	}
+
+	pub fn update(&mut self, data: &[u8]) {
+		self.0.update(data)
+	}
+
+	pub fn digest(&self) -> [u8; 32] {
+		self.0.finish()
+	}
+}
+
+impl Sha384 {
+	pub fn new() -> Sha384 {
+		Sha384(Engine512::new(SHA384_INIT))
+	}
+
+	pub fn from_state(state: [u64; 8], length: u64) -> Result<Sha384, CryptoError> {
+		Ok(Sha384(Engine512::from_state(state, length)?))
+	}
+
+	pub fn state(&self) -> [u64; 8] {
+		self.0.state
+	}
+
+	pub fn length(&self) -> u64 {
+		self.0.length
+	}
+
+	pub fn update(&mut self, data: &[u8]) {
+		self.0.update(data)
+	}
+
+	pub fn digest(&self) -> [u8; 48] {
+		let mut digest = [0u8; 48];
+		digest.copy_from_slice(&self.0.finish()[..48]);
+		digest
+	}
+}
+
+impl Sha512 {
+	pub fn new() -> Sha512 {
+		Sha512(Engine512::new(SHA512_INIT))
+	}
+
+	pub fn from_state(state: [u64; 8], length: u64) -> Result<Sha512, CryptoError> {
+		Ok(Sha512(Engine512::from_state(state, length)?))
+	}
+
+	pub fn state(&self) -> [u64; 8] {
+		self.0.state
+	}
+
+	pub fn length(&self) -> u64 {
+		self.0.length
+	}
+
+	pub fn update(&mut self, data: &[u8]) {
+		self.0.update(data)
+	}
+
+	pub fn digest(&self) -> [u8; 64] {
+		self.0.finish()
+	}
+}
+
+pub fn sha224(data: &[u8]) -> [u8; 28] {
+	let mut h = Sha224::new();
+	h.update(data);
+	h.digest()
+}
+
+pub fn sha256(data: &[u8]) -> [u8; 32] {
+	let mut h = Sha256::new();
+	h.update(data);
+	h.digest()
+}
+
+pub fn sha384(data: &[u8]) -> [u8; 48] {
+	let mut h = Sha384::new();
+	h.update(data);
+	h.digest()
+}
+
+pub fn sha512(data: &[u8]) -> [u8; 64] {
+	let mut h = Sha512::new();
+	h.update(data);
+	h.digest()
+}
+
+impl Hash for Sha224 {
+	const BLOCK_SIZE: usize = SHA256_BLOCK_SIZE;
+	const DIGEST_SIZE: usize = 28;
+
+	fn new() -> Sha224 {
+		Sha224::new()
+	}
+
+	fn update(&mut self, data: &[u8]) {
+		Sha224::update(self, data)
+	}
+
+	fn digest(&self) -> Vec<u8> {
+		Sha224::digest(self).to_vec()
+	}
+}
+
+impl Hash for Sha256 {
+	const BLOCK_SIZE: usize = SHA256_BLOCK_SIZE;
+	const DIGEST_SIZE: usize = 32;
+
+	fn new() -> Sha256 {
+		Sha256::new()
+	}
+
+	fn update(&mut self, data: &[u8]) {
+		Sha256::update(self, data)
+	}
+
+	fn digest(&self) -> Vec<u8> {
+		Sha256::digest(self).to_vec()
+	}
+}
+
+impl Hash for Sha384 {
+	const BLOCK_SIZE: usize = SHA512_BLOCK_SIZE;
+	const DIGEST_SIZE: usize = 48;
+
+	fn new() -> Sha384 {
+		Sha384::new()
+	}
+
+	fn update(&mut self, data: &[u8]) {
+		Sha384::update(self, data)
+	}
+
+	fn digest(&self) -> Vec<u8> {
+		Sha384::digest(self).to_vec()
+	}
+}
+
+impl Hash for Sha512 {
+	const BLOCK_SIZE: usize = SHA512_BLOCK_SIZE;
+	const DIGEST_SIZE: usize = 64;
+
+	fn new() -> Sha512 {
+		Sha512::new()
+	}
+
+	fn update(&mut self, data: &[u8]) {
+		Sha512::update(self, data)
+	}
+
+	fn digest(&self) -> Vec<u8> {
+		Sha512::digest(self).to_vec()
+	}
+}
+
+// the truncated variants don't reveal their whole state,
+// so only SHA-256 and SHA-512 can be resumed from a digest
+impl InjectableHash for Sha256 {
+	fn md_padding(len: u64) -> Vec<u8> {
+		sha256_padding(len)
+	}
+
+	fn from_digest(digest: &[u8], length: u64) -> Result<Sha256, CryptoError> {
+		if digest.len() != 32 {
+			return Err(CryptoError::DigestLength(digest.len()));
+		}
+		Sha256::from_state(sha256_state_from_digest(digest), length)
+	}
+}
+
+impl InjectableHash for Sha512 {
+	fn md_padding(len: u64) -> Vec<u8> {
+		sha512_padding(len)
+	}
+
+	fn from_digest(digest: &[u8], length: u64) -> Result<Sha512, CryptoError> {
+		if digest.len() != 64 {
+			return Err(CryptoError::DigestLength(digest.len()));
+		}
+		Sha512::from_state(sha512_state_from_digest(digest), length)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use utils::cryptodata::{CryptoData, CryptoError};
+	use utils::hash::InjectableHash;
+	use utils::sha2::{sha224, sha256, sha384, sha512, Sha224, Sha256, Sha384, Sha512};
+
+	// FIPS 180-2 examples
+	const TWO_BLOCKS: &[u8] = b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq";
+
+	fn hex(digest: &[u8]) -> String {
+		CryptoData::from_slice(digest).to_hex()
+	}
+
+	#[test]
+	fn sha224_vectors() {
+		assert_eq!(hex(&sha224(b"abc")), "23097d223405d8228642a477bda255b32aadbce4bda0b3f7e36c9da7");
+		assert_eq!(hex(&sha224(b"")), "d14a028c2a3a2bc9476102bb288234c415a2b01f828ea62ac5b3e42f");
+		assert_eq!(hex(&sha224(TWO_BLOCKS)), "75388b16512776cc5dba5da1fd890150b0c6455cb4f58b1952522525");
+	}
+
+	#[test]
+	fn sha256_vectors() {
+		assert_eq!(hex(&sha256(b"abc")), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+		assert_eq!(hex(&sha256(b"")), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+		assert_eq!(hex(&sha256(TWO_BLOCKS)), "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1");
+	}
+
+	#[test]
+	fn sha384_vectors() {
+		assert_eq!(hex(&sha384(b"abc")), "cb00753f45a35e8bb5a03d699ac65007272c32ab0eded163\
+			1a8b605a43ff5bed8086072ba1e7cc2358baeca134c825a7");
+		assert_eq!(hex(&sha384(b"")), "38b060a751ac96384cd9327eb1b1e36a21fdb71114be0743\
+			4c0cc7bf63f6e1da274edebfe76f65fbd51ad2f14898b95b");
+		assert_eq!(hex(&sha384(TWO_BLOCKS)), "3391fdddfc8dc7393707a65b1b4709397cf8b1d162af05ab\
+			fe8f450de5f36bc6b0455a8520bc4e6f5fe95b1fe3c8452b");
+	}
+
+	#[test]
+	fn sha512_vectors() {
+		assert_eq!(hex(&sha512(b"abc")), "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+			2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f");
+		assert_eq!(hex(&sha512(b"")), "cf83e1357eefb8bdf1542850d66d8007d620e4050b5715dc83f4a921d36ce9ce\
+			47d0d13c5d85f2b0ff8318d2877eec2f63b931bd47417a81a538327af927da3e");
+		assert_eq!(hex(&sha512(TWO_BLOCKS)), "204a8fc6dda82f0a0ced7beb8e08a41657c16ef468b228a8279be331a703c335\
+			96fd15c13b1b07f9aa1d3bea57789ca031ad85c7a71dd70354ec631238ca3445");
+	}
+
+	// H(m || padding || suffix) from H(m) alone
+	fn check_from_digest<H: InjectableHash>() {
+		let msg: Vec<u8> = (0..300u32).map(|i| (i * 13) as u8).collect();
+		let (prefix, suffix) = msg.split_at(77);
+
+		let mut glued = prefix.to_vec();
+		glued.extend_from_slice(&H::md_padding(prefix.len() as u64));
+		let mut resumed = H::from_digest(&H::hash(prefix), glued.len() as u64).unwrap();
+		resumed.update(suffix);
+		glued.extend_from_slice(suffix);
+		assert_eq!(resumed.digest(), H::hash(&glued));
+
+		assert!(match H::from_digest(&H::hash(prefix), glued.len() as u64 - 1) {
+			Err(CryptoError::BlockLength(_, bsize)) => bsize == H::BLOCK_SIZE,
+			_ => false,
+		});
+	}
+
+	#[test]
+	fn from_digest_resumes() {
+		check_from_digest::<Sha256>();
+		check_from_digest::<Sha512>();
+	}
+
+	#[test]
+	fn from_state_resumes() {
+		let msg: Vec<u8> = (0..300u32).map(|i| (i * 13) as u8).collect();
+
+		let mut h = Sha224::new();
+		h.update(&msg[..128]);
+		let mut resumed = Sha224::from_state(h.state(), h.length()).unwrap();
+		resumed.update(&msg[128..]);
+		assert_eq!(resumed.digest(), sha224(&msg));
+
+		let mut h = Sha256::new();
+		h.update(&msg[..192]);
+		let mut resumed = Sha256::from_state(h.state(), h.length()).unwrap();
+		resumed.update(&msg[192..]);
+		assert_eq!(resumed.digest(), sha256(&msg));
+
+		let mut h = Sha384::new();
+		h.update(&msg[..128]);
+		let mut resumed = Sha384::from_state(h.state(), h.length()).unwrap();
+		resumed.update(&msg[128..]);
+		assert_eq!(&resumed.digest()[..], &sha384(&msg)[..]);
+
+		let mut h = Sha512::new();
+		h.update(&msg[..256]);
+		let mut resumed = Sha512::from_state(h.state(), h.length()).unwrap();
+		resumed.update(&msg[256..]);
+		assert_eq!(&resumed.digest()[..], &sha512(&msg)[..]);
+
+		assert!(Sha512::from_state(h.state(), 64).is_err());
+	}
+}
@@ -0,0 +1,627 @@
+// Secure Remote Password (SRP-6a) over SHA-256, following the challenge:
+// x = H(salt || password), u = H(A || B), K = H(S)
+// and the client proves it knows K with HMAC-SHA256(K, salt)
+
+use std::collections::HashMap;
+
+use utils::bignum::BigInt;
+use utils::cryptodata::{CryptoData, CryptoError};
+use utils::dh::DhGroup;
+use utils::hash::Hash;
+use utils::hmac;
+use utils::protocol::{ProtocolError, Transport, WireFormat};
+use utils::sha2::Sha256;
+
+pub const SALT_LEN: usize = 16;
+
+#[derive (Clone, Debug)]
+pub struct SrpParams {
+	pub n: BigInt,
+	pub g: BigInt,
+	// multiplier of the verifier in B = kv + g^b
+	pub k: BigInt,
+}
+
+impl SrpParams {
+	// the challenge's parameters, NIST prime with k = 3
+	pub fn nist() -> SrpParams {
+		let group = DhGroup::nist();
+		SrpParams { n: group.p, g: group.g, k: BigInt::from_u32(3) }
+	}
+
+	// SRP-6a proper, k = H(N || PAD(g))
+	pub fn srp6a(group: &DhGroup) -> SrpParams {
+		let len = group.element_len();
+		let k = hash_int(&[&group.p.to_bytes(), &group.g.to_bytes_padded(len)]);
+		SrpParams { n: group.p.clone(), g: group.g.clone(), k }
+	}
+}
+
+// SHA-256 of the concatenated parts as an integer
+pub fn hash_int(parts: &[&[u8]]) -> BigInt {
+	let mut h = Sha256::new();
+	for part in parts {
+		h.update(part);
+	}
+	BigInt::from_bytes(&h.digest())
+}
+
+// x = H(salt || password)
+pub fn private_key(salt: &[u8], password: &[u8]) -> BigInt {
+	hash_int(&[salt, password])
+}
+
+// u = H(A || B)
+pub fn scrambler(a: &BigInt, b: &BigInt) -> BigInt {
+	hash_int(&[&a.to_bytes(), &b.to_bytes()])
+}
+
+// K = H(S)
+pub fn session_key(s: &BigInt) -> CryptoData {
+	CryptoData::from_owned(Sha256::hash(&s.to_bytes()))
+}
+
+pub fn proof(key: &CryptoData, salt: &CryptoData) -> CryptoData {
+	salt.HMAC::<Sha256>(key)
+}
+
+#[derive (Clone, Debug)]
+pub struct Verifier {
+	pub salt: CryptoData,
+	pub v: BigInt,
+}
+
+impl Verifier {
+	pub fn new(params: &SrpParams, password: &[u8]) -> Verifier {
+		let salt = CryptoData::random(SALT_LEN);
+		let x = private_key(&salt, password);
+		let v = params.g.modexp(&x, &params.n);
+		Verifier { salt, v }
+	}
+}
+
+// What the server stores instead of passwords
+#[derive (Clone, Debug, Default)]
+pub struct VerifierStore {
+	users: HashMap<String, Verifier>,
+}
+
+impl VerifierStore {
+	pub fn new() -> VerifierStore {
+		VerifierStore { users: HashMap::new() }
+	}
+
+	pub fn register(&mut self, params: &SrpParams, identity: &str, password: &[u8]) {
+		self.users.insert(identity.to_string(), Verifier::new(params, password));
+	}
+
+	pub fn get(&self, identity: &str) -> Option<&Verifier> {
+		self.users.get(identity)
+	}
+}
+
+#[derive (Clone, Debug)]
+pub struct SrpServer {
+	params: SrpParams,
+	store: VerifierStore,
+	// reject A = 0 mod N, which would fix S = 0
+	hardened: bool,
+}
+
+impl SrpServer {
+	// the naive server from the challenge, see set_hardened()
+	pub fn new(params: SrpParams, store: VerifierStore) -> SrpServer {
+		SrpServer { params, store, hardened: false }
+	}
+
+	pub fn params(&self) -> &SrpParams {
+		&self.params
+	}
+
+	pub fn set_hardened(&mut self, hardened: bool) {
+		self.hardened = hardened;
+	}
+
+	// a login attempt of identity with the client's public key A
+	pub fn start(&self, identity: &str, a: &BigInt) -> Result<SrpServerSession, CryptoError> {
+		let verifier = self.store.get(identity).ok_or(CryptoError::Authentication)?;
+		let params = &self.params;
+		if self.hardened && a.modulo(&params.n).is_zero() {
+			return Err(CryptoError::PublicKey);
+		}
+
+		let b = BigInt::random_range(&BigInt::one(), &params.n);
+		// B = kv + g^b
+		let big_b = (&(&params.k * &verifier.v) + &params.g.modexp(&b, &params.n)).modulo(&params.n);
+
+		// S = (A * v^u)^b
+		let u = scrambler(a, &big_b);
+		let s = (a * &verifier.v.modexp(&u, &params.n)).modexp(&b, &params.n);
+
+		Ok(SrpServerSession { salt: verifier.salt.clone(), b: big_b, key: session_key(&s) })
+	}
+}
+
+// Server side of one login, between sending the challenge and checking the proof
+pub struct SrpServerSession {
+	salt: CryptoData,
+	b: BigInt,
+	key: CryptoData,
+}
+
+impl SrpServerSession {
+	// salt and B for the client
+	pub fn challenge(&self) -> (CryptoData, BigInt) {
+		(self.salt.clone(), self.b.clone())
+	}
+
+	// the session key if the client's proof is right
+	pub fn verify(&self, client_proof: &CryptoData) -> Result<CryptoData, CryptoError> {
+		if hmac::hmac_verify::<Sha256>(&self.key, &self.salt, client_proof) {
+			Ok(self.key.clone())
+		} else {
+			Err(CryptoError::Authentication)
+		}
+	}
+}
+
+pub struct SrpClient {
+	params: SrpParams,
+	identity: String,
+	password: Vec<u8>,
+	a: BigInt,
+	public: BigInt,
+}
+
+impl SrpClient {
+	pub fn new(params: SrpParams, identity: &str, password: &[u8]) -> SrpClient {
+		let a = BigInt::random_range(&BigInt::one(), &params.n);
+		let public = params.g.modexp(&a, &params.n);
+		SrpClient { params, identity: identity.to_string(), password: password.to_vec(), a, public }
+	}
+
+	pub fn identity(&self) -> &str {
+		&self.identity
+	}
+
+	// A = g^a
+	pub fn public(&self) -> &BigInt {
+		&self.public
+	}
+
+	// the session key and its proof for the server's challenge
+	pub fn respond(&self, salt: &CryptoData, big_b: &BigInt) -> Result<(CryptoData, CryptoData), CryptoError> {
+		let params = &self.params;
+		let u = scrambler(&self.public, big_b);
+		// the server could force S otherwise
+		if big_b.modulo(&params.n).is_zero() || u.is_zero() {
+			return Err(CryptoError::PublicKey);
+		}
+
+		// S = (B - kg^x)^(a + ux)
+		let x = private_key(salt, &self.password);
+		let kgx = &params.k * &params.g.modexp(&x, &params.n);
+		let base = (big_b - &kgx).modulo(&params.n);
+		let s = base.modexp(&(&self.a + &(&u * &x)), &params.n);
+
+		let key = session_key(&s);
+		let client_proof = proof(&key, salt);
+		Ok((key, client_proof))
+	}
+
+	// the same for the simplified protocol, S = B^(a + ux)
+	pub fn respond_simple(&self, salt: &CryptoData, big_b: &BigInt, u: &BigInt) -> Result<(CryptoData, CryptoData), CryptoError> {
+		let params = &self.params;
+		if big_b.modulo(&params.n).is_zero() {
+			return Err(CryptoError::PublicKey);
+		}
+
+		let x = private_key(salt, &self.password);
+		let s = big_b.modexp(&(&self.a + &(u * &x)), &params.n);
+
+		let key = session_key(&s);
+		let client_proof = proof(&key, salt);
+		Ok((key, client_proof))
+	}
+}
+
+// Simplified SRP from chal38: B = g^b doesn't involve the verifier
+// and u is a random 128-bit number sent along with it
+#[derive (Clone, Debug)]
+pub struct SimpleSrpServer {
+	params: SrpParams,
+	store: VerifierStore,
+}
+
+impl SimpleSrpServer {
+	pub fn new(params: SrpParams, store: VerifierStore) -> SimpleSrpServer {
+		SimpleSrpServer { params, store }
+	}
+
+	pub fn start(&self, identity: &str, a: &BigInt) -> Result<SimpleSrpSession, CryptoError> {
+		let verifier = self.store.get(identity).ok_or(CryptoError::Authentication)?;
+		let params = &self.params;
+
+		let b = BigInt::random_range(&BigInt::one(), &params.n);
+		let big_b = params.g.modexp(&b, &params.n);
+		let u = BigInt::from(&CryptoData::random(16));
+
+		// S = (A * v^u)^b
+		let s = (a * &verifier.v.modexp(&u, &params.n)).modexp(&b, &params.n);
+
+		let session = SrpServerSession { salt: verifier.salt.clone(), b: big_b, key: session_key(&s) };
+		Ok(SimpleSrpSession { session, u })
+	}
+}
+
+pub struct SimpleSrpSession {
+	session: SrpServerSession,
+	u: BigInt,
+}
+
+impl SimpleSrpSession {
+	// salt, B and u for the client
+	pub fn challenge(&self) -> (CryptoData, BigInt, BigInt) {
+		let (salt, b) = self.session.challenge();
+		(salt, b, self.u.clone())
+	}
+
+	pub fn verify(&self, client_proof: &CryptoData) -> Result<CryptoData, CryptoError> {
+		self.session.verify(client_proof)
+	}
+}
+
+#[derive (Clone, Debug, PartialEq, Eq)]
+pub enum SrpMessage {
+	// I, A; the identity goes over the wire hex encoded
+	// so it can contain spaces
+	Hello(String, BigInt),
+	// salt, B
+	Challenge(CryptoData, BigInt),
+	// salt, B, u of the simplified protocol
+	SimpleChallenge(CryptoData, BigInt, BigInt),
+	// HMAC-SHA256(K, salt)
+	Proof(CryptoData),
+	// whether the proof was accepted
+	Result(bool),
+}
+
+fn parse_hex(field: Option<&str>) -> Result<CryptoData, ProtocolError> {
+	let field = field.ok_or(ProtocolError::Unexpected("hex field"))?;
+	Ok(CryptoData::try_from_hex(field)?)
+}
+
+fn parse_int(field: Option<&str>) -> Result<BigInt, ProtocolError> {
+	let field = field.ok_or(ProtocolError::Unexpected("number"))?;
+	Ok(BigInt::try_from_hex(field)?)
+}
+
+impl WireFormat for SrpMessage {
+	fn to_line(&self) -> String {
+		match *self {
+			SrpMessage::Hello(ref identity, ref a) =>
+				format!("HELLO {} {}", CryptoData::from_text(identity).to_hex(), a.to_hex()),
+			SrpMessage::Challenge(ref salt, ref b) => format!("CHALLENGE {} {}", salt.to_hex(), b.to_hex()),
+			SrpMessage::SimpleChallenge(ref salt, ref b, ref u) =>
+				format!("SIMPLE-CHALLENGE {} {} {}", salt.to_hex(), b.to_hex(), u.to_hex()),
+			SrpMessage::Proof(ref proof) => format!("PROOF {}", proof.to_hex()),
+			SrpMessage::Result(ok) => (if ok { "OK" } else { "FAIL" }).to_string(),
+		}
+	}
+
+	fn from_line(line: &str) -> Result<SrpMessage, ProtocolError> {
+		let mut fields = line.split(' ');
+		let msg = match fields.next() {
+			Some("HELLO") => {
+				let identity = parse_hex(fields.next())?.try_to_text()?;
+				SrpMessage::Hello(identity, parse_int(fields.next())?)
+			},
+			Some("CHALLENGE") => {
+				let salt = parse_hex(fields.next())?;
+				SrpMessage::Challenge(salt, parse_int(fields.next())?)
+			},
+			Some("SIMPLE-CHALLENGE") => {
+				let salt = parse_hex(fields.next())?;
+				let b = parse_int(fields.next())?;
+				SrpMessage::SimpleChallenge(salt, b, parse_int(fields.next())?)
+			},
+			Some("PROOF") => SrpMessage::Proof(parse_hex(fields.next())?),
+			Some("OK") => SrpMessage::Result(true),
+			Some("FAIL") => SrpMessage::Result(false),
+			_ => return Err(ProtocolError::Unexpected("SRP message")),
+		};
+		// all the fields have to be used
+		match fields.next() {
+			Some(_) => Err(ProtocolError::Unexpected("end of SRP message")),
+			None => Ok(msg),
+		}
+	}
+}
+
+// Log in over any transport, returns the session key if the server accepted
+pub fn client_login<T: Transport<SrpMessage>>(transport: &mut T, client: &SrpClient) -> Result<CryptoData, ProtocolError> {
+	transport.send(SrpMessage::Hello(client.identity().to_string(), client.public().clone()))?;
+	let (salt, b) = match transport.recv()? {
+		SrpMessage::Challenge(salt, b) => (salt, b),
+		SrpMessage::Result(false) => return Err(ProtocolError::Crypto(CryptoError::Authentication)),
+		_ => return Err(ProtocolError::Unexpected("challenge")),
+	};
+
+	let (key, client_proof) = client.respond(&salt, &b)?;
+	send_proof(transport, key, client_proof)
+}
+
+// last step of the client, the key is returned if the server accepts the proof
+fn send_proof<T: Transport<SrpMessage>>(transport: &mut T, key: CryptoData, client_proof: CryptoData) -> Result<CryptoData, ProtocolError> {
+	transport.send(SrpMessage::Proof(client_proof))?;
+	match transport.recv()? {
+		SrpMessage::Result(true) => Ok(key),
+		SrpMessage::Result(false) => Err(ProtocolError::Crypto(CryptoError::Authentication)),
+		_ => Err(ProtocolError::Unexpected("result")),
+	}
+}
+
+// Handle one login, returns the identity and session key on success
+pub fn server_login<T: Transport<SrpMessage>>(transport: &mut T, server: &SrpServer) -> Result<(String, CryptoData), ProtocolError> {
+	let (identity, a) = match transport.recv()? {
+		SrpMessage::Hello(identity, a) => (identity, a),
+		_ => return Err(ProtocolError::Unexpected("hello")),
+	};
+
+	let session = match server.start(&identity, &a) {
+		Ok(session) => session,
+		Err(e) => {
+			transport.send(SrpMessage::Result(false))?;
+			return Err(e.into());
+		},
+	};
+	let (salt, b) = session.challenge();
+	transport.send(SrpMessage::Challenge(salt, b))?;
+
+	let key = check_proof(transport, |proof| session.verify(proof))?;
+	Ok((identity, key))
+}
+
+// last step of the server, tell the client whether its proof is right
+fn check_proof<T, F>(transport: &mut T, verify: F) -> Result<CryptoData, ProtocolError>
+	where T: Transport<SrpMessage>, F: FnOnce(&CryptoData) -> Result<CryptoData, CryptoError> {
+	let client_proof = match transport.recv()? {
+		SrpMessage::Proof(proof) => proof,
+		_ => return Err(ProtocolError::Unexpected("proof")),
+	};
+	match verify(&client_proof) {
+		Ok(key) => {
+			transport.send(SrpMessage::Result(true))?;
+			Ok(key)
+		},
+		Err(e) => {
+			transport.send(SrpMessage::Result(false))?;
+			Err(e.into())
+		},
+	}
+}
+
+// Log in to a simplified SRP server
+pub fn simple_client_login<T: Transport<SrpMessage>>(transport: &mut T, client: &SrpClient) -> Result<CryptoData, ProtocolError> {
+	transport.send(SrpMessage::Hello(client.identity().to_string(), client.public().clone()))?;
+	let (salt, b, u) = match transport.recv()? {
+		SrpMessage::SimpleChallenge(salt, b, u) => (salt, b, u),
+		SrpMessage::Result(false) => return Err(ProtocolError::Crypto(CryptoError::Authentication)),
+		_ => return Err(ProtocolError::Unexpected("challenge")),
+	};
+
+	let (key, client_proof) = client.respond_simple(&salt, &b, &u)?;
+	send_proof(transport, key, client_proof)
+}
+
+// Handle one simplified SRP login, returns the identity and session key on success
+pub fn simple_server_login<T: Transport<SrpMessage>>(transport: &mut T, server: &SimpleSrpServer) -> Result<(String, CryptoData), ProtocolError> {
+	let (identity, a) = match transport.recv()? {
+		SrpMessage::Hello(identity, a) => (identity, a),
+		_ => return Err(ProtocolError::Unexpected("hello")),
+	};
+
+	let session = match server.start(&identity, &a) {
+		Ok(session) => session,
+		Err(e) => {
+			transport.send(SrpMessage::Result(false))?;
+			return Err(e.into());
+		},
+	};
+	let (salt, b, u) = session.challenge();
+	transport.send(SrpMessage::SimpleChallenge(salt, b, u))?;
+
+	let key = check_proof(transport, |proof| session.verify(proof))?;
+	Ok((identity, key))
+}
+
+#[cfg(test)]
+mod tests {
+	use std::thread;
+
+	use utils::bignum::BigInt;
+	use utils::cryptodata::{CryptoData, CryptoError};
+	use utils::dh::DhGroup;
+	use utils::protocol::{link, ProtocolError, WireFormat};
+	use utils::srp::{client_login, proof, server_login, session_key, simple_client_login, simple_server_login,
+		SimpleSrpServer, SrpClient, SrpMessage, SrpParams, SrpServer, VerifierStore};
+
+	const IDENTITY: &str = "alice@example.com";
+	const PASSWORD: &[u8] = b"correct horse battery staple";
+
+	fn server(hardened: bool) -> SrpServer {
+		let params = SrpParams::nist();
+		let mut store = VerifierStore::new();
+		store.register(&params, IDENTITY, PASSWORD);
+		let mut server = SrpServer::new(params, store);
+		server.set_hardened(hardened);
+		server
+	}
+
+	// A = 0, N and 2N, all of them give S = 0 on the server
+	fn zero_keys() -> Vec<BigInt> {
+		let n = SrpParams::nist().n;
+		vec![BigInt::zero(), n.clone(), &n * &BigInt::from_u32(2)]
+	}
+
+	#[test]
+	fn honest_login() {
+		for &hardened in [false, true].iter() {
+			let srv = server(hardened);
+			let client = SrpClient::new(SrpParams::nist(), IDENTITY, PASSWORD);
+			let session = srv.start(IDENTITY, client.public()).unwrap();
+			let (salt, b) = session.challenge();
+			let (key, client_proof) = client.respond(&salt, &b).unwrap();
+			assert_eq!(session.verify(&client_proof).unwrap(), key);
+
+			let wrong = SrpClient::new(SrpParams::nist(), IDENTITY, b"hunter2");
+			let session = srv.start(IDENTITY, wrong.public()).unwrap();
+			let (salt, b) = session.challenge();
+			let (_, client_proof) = wrong.respond(&salt, &b).unwrap();
+			assert!(session.verify(&client_proof).is_err());
+		}
+	}
+
+	#[test]
+	fn honest_login_srp6a() {
+		let params = SrpParams::srp6a(&DhGroup::nist());
+		assert!(params.k != BigInt::from_u32(3));
+		let mut store = VerifierStore::new();
+		store.register(&params, IDENTITY, PASSWORD);
+		let srv = SrpServer::new(params.clone(), store);
+
+		let client = SrpClient::new(params.clone(), IDENTITY, PASSWORD);
+		let session = srv.start(IDENTITY, client.public()).unwrap();
+		let (salt, b) = session.challenge();
+		let (key, client_proof) = client.respond(&salt, &b).unwrap();
+		assert_eq!(session.verify(&client_proof).unwrap(), key);
+
+		let wrong = SrpClient::new(params, IDENTITY, b"hunter2");
+		let session = srv.start(IDENTITY, wrong.public()).unwrap();
+		let (salt, b) = session.challenge();
+		let (_, client_proof) = wrong.respond(&salt, &b).unwrap();
+		assert!(matches!(session.verify(&client_proof), Err(CryptoError::Authentication)));
+	}
+
+	fn simple_server() -> SimpleSrpServer {
+		let params = SrpParams::nist();
+		let mut store = VerifierStore::new();
+		store.register(&params, IDENTITY, PASSWORD);
+		SimpleSrpServer::new(params, store)
+	}
+
+	#[test]
+	fn simple_login() {
+		let srv = simple_server();
+		let client = SrpClient::new(SrpParams::nist(), IDENTITY, PASSWORD);
+		let session = srv.start(IDENTITY, client.public()).unwrap();
+		let (salt, b, u) = session.challenge();
+		let (key, client_proof) = client.respond_simple(&salt, &b, &u).unwrap();
+		assert_eq!(session.verify(&client_proof).unwrap(), key);
+
+		let wrong = SrpClient::new(SrpParams::nist(), IDENTITY, b"hunter2");
+		let session = srv.start(IDENTITY, wrong.public()).unwrap();
+		let (salt, b, u) = session.challenge();
+		let (_, client_proof) = wrong.respond_simple(&salt, &b, &u).unwrap();
+		assert!(matches!(session.verify(&client_proof), Err(CryptoError::Authentication)));
+
+		assert!(matches!(srv.start("bob@example.com", client.public()), Err(CryptoError::Authentication)));
+	}
+
+	#[test]
+	fn simple_login_over_protocol() {
+		for &(password, ok) in [(PASSWORD, true), (&b"hunter2"[..], false)].iter() {
+			let (mut client_ep, mut server_ep) = link::<SrpMessage>();
+			let srv = simple_server();
+			let handle = thread::spawn(move || simple_server_login(&mut server_ep, &srv));
+			let client = SrpClient::new(SrpParams::nist(), IDENTITY, password);
+			let login = simple_client_login(&mut client_ep, &client);
+			drop(client_ep);
+			let accepted = handle.join().unwrap();
+
+			if ok {
+				let (identity, key) = accepted.unwrap();
+				assert_eq!(identity, IDENTITY);
+				assert_eq!(login.unwrap(), key);
+			} else {
+				assert!(matches!(login, Err(ProtocolError::Crypto(CryptoError::Authentication))));
+				assert!(matches!(accepted, Err(ProtocolError::Crypto(CryptoError::Authentication))));
+			}
+		}
+	}
+
+	#[test]
+	fn zero_key_bypasses_default_server() {
+		let srv = server(false);
+		for a in zero_keys() {
+			let session = srv.start(IDENTITY, &a).unwrap();
+			let (salt, _) = session.challenge();
+			// no password needed
+			let key = session_key(&BigInt::zero());
+			assert_eq!(session.verify(&proof(&key, &salt)).unwrap(), key);
+		}
+	}
+
+	#[test]
+	fn zero_key_rejected_by_hardened_server() {
+		let srv = server(true);
+		for a in zero_keys() {
+			assert!(matches!(srv.start(IDENTITY, &a), Err(CryptoError::PublicKey)));
+		}
+	}
+
+	#[test]
+	fn login_over_protocol() {
+		for &hardened in [false, true].iter() {
+			let (mut client_ep, mut server_ep) = link::<SrpMessage>();
+			let srv = server(hardened);
+			let handle = thread::spawn(move || server_login(&mut server_ep, &srv));
+			let client = SrpClient::new(SrpParams::nist(), IDENTITY, PASSWORD);
+			let key = client_login(&mut client_ep, &client).unwrap();
+			drop(client_ep);
+			let (identity, accepted) = handle.join().unwrap().unwrap();
+			assert_eq!(identity, IDENTITY);
+			assert_eq!(accepted, key);
+
+			let (mut client_ep, mut server_ep) = link::<SrpMessage>();
+			let srv = server(hardened);
+			let handle = thread::spawn(move || server_login(&mut server_ep, &srv));
+			let client = SrpClient::new(SrpParams::nist(), IDENTITY, b"hunter2");
+			assert!(client_login(&mut client_ep, &client).is_err());
+			drop(client_ep);
+			assert!(matches!(handle.join().unwrap(), Err(ProtocolError::Crypto(CryptoError::Authentication))));
+		}
+	}
+
+	fn round_trip(msg: SrpMessage) {
+		let line = msg.to_line();
+		assert!(!line.contains('\n'));
+		assert_eq!(SrpMessage::from_line(&line).unwrap(), msg);
+	}
+
+	#[test]
+	fn wire_format_round_trip() {
+		let a = BigInt::from_hex("deadbeef0123456789");
+		round_trip(SrpMessage::Hello("alice@example.com".to_string(), a.clone()));
+		round_trip(SrpMessage::Hello("Alice Liddell".to_string(), a.clone()));
+		round_trip(SrpMessage::Hello("".to_string(), a.clone()));
+		round_trip(SrpMessage::Challenge(CryptoData::random(16), a.clone()));
+		round_trip(SrpMessage::SimpleChallenge(CryptoData::random(16), a.clone(), BigInt::from_u32(7)));
+		round_trip(SrpMessage::Proof(CryptoData::random(32)));
+		round_trip(SrpMessage::Result(true));
+		round_trip(SrpMessage::Result(false));
+	}
+
+	#[test]
+	fn wire_format_rejects_garbage() {
+		assert!(SrpMessage::from_line("HELLO alice 1234").is_err());
+		assert!(SrpMessage::from_line("HELLO ff 1234").is_err());
+		assert!(SrpMessage::from_line("CHALLENGE 00").is_err());
+		assert!(SrpMessage::from_line("GOODBYE").is_err());
+		// leftover fields
+		assert!(SrpMessage::from_line("PROOF aa bb").is_err());
+		assert!(SrpMessage::from_line("OK OK").is_err());
+		assert!(SrpMessage::from_line("CHALLENGE 00 1234 ").is_err());
+		assert!(SrpMessage::from_line("SIMPLE-CHALLENGE 00 12 34 56").is_err());
+	}
+}
@@ -0,0 +1,361 @@
+use std::io;
+use std::io::{Read, Write};
+use std::mem;
+
+use utils::blockcipher::BlockCipher;
+use utils::cryptodata::{xor_slice, CryptoError};
+use utils::ctr::{CounterBlock, CtrKeystream};
+use utils::padding::{Padding, Pkcs7};
+
+// Encrypts everything written to it in CTR mode and passes it on
+pub struct CtrWriter<C: BlockCipher, W: Write> {
+	inner: W,
+	keystream: CtrKeystream<C>,
+	offset: usize,
+}
+
+impl<C: BlockCipher, W: Write> CtrWriter<C, W> {
+	pub fn new(inner: W, cipher: C, start: CounterBlock) -> CtrWriter<C, W> {
+		CtrWriter { inner, keystream: CtrKeystream::new(cipher, start), offset: 0 }
+	}
+
+	pub fn into_inner(self) -> W {
+		self.inner
+	}
+}
+
+impl<C: BlockCipher, W: Write> Write for CtrWriter<C, W> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		let mut enc = buf.to_vec();
+		self.keystream.apply(self.offset, &mut enc);
+		// the keystream position must match what was really written
+		self.inner.write_all(&enc)?;
+		self.offset += enc.len();
+		Ok(enc.len())
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		self.inner.flush()
+	}
+}
+
+// Decrypts (or encrypts, it's the same) a CTR stream while reading
+pub struct CtrReader<C: BlockCipher, R: Read> {
+	inner: R,
+	keystream: CtrKeystream<C>,
+	offset: usize,
+}
+
+impl<C: BlockCipher, R: Read> CtrReader<C, R> {
+	pub fn new(inner: R, cipher: C, start: CounterBlock) -> CtrReader<C, R> {
+		CtrReader { inner, keystream: CtrKeystream::new(cipher, start), offset: 0 }
+	}
+}
+
+impl<C: BlockCipher, R: Read> Read for CtrReader<C, R> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		let count = self.inner.read(buf)?;
+		self.keystream.apply(self.offset, &mut buf[..count]);
+		self.offset += count;
+		Ok(count)
+	}
+}
+
+// CBC encryption with PKCS#7 padding,
+// finish() has to be called to write out the last padded block
+pub struct CbcEncryptWriter<C: BlockCipher, W: Write> {
+	inner: W,
+	cipher: C,
+	// previous ciphertext block
+	prev: Vec<u8>,
+	// incomplete block waiting for more data
+	partial: Vec<u8>,
+}
+
+impl<C: BlockCipher, W: Write> CbcEncryptWriter<C, W> {
+	pub fn new(inner: W, cipher: C, iv: &[u8]) -> Result<CbcEncryptWriter<C, W>, CryptoError> {
+		let bsize = cipher.block_size();
+		if iv.len() != bsize {
+			return Err(CryptoError::IvLength(iv.len()));
+		}
+		Ok(CbcEncryptWriter { inner, cipher, prev: iv.to_vec(), partial: Vec::with_capacity(bsize) })
+	}
+
+	fn encrypt_blocks(&mut self, data: &mut [u8]) {
+		let bsize = self.cipher.block_size();
+		for block in data.chunks_mut(bsize) {
+			xor_slice(block, &self.prev);
+			self.cipher.encrypt_block(block);
+			self.prev.copy_from_slice(block);
+		}
+	}
+
+	// pad and write the last block, returns the inner writer
+	pub fn finish(mut self) -> io::Result<W> {
+		let bsize = self.cipher.block_size();
+		let mut last = mem::take(&mut self.partial);
+		Pkcs7.pad(&mut last, bsize);
+		self.encrypt_blocks(&mut last);
+		self.inner.write_all(&last)?;
+		self.inner.flush()?;
+		Ok(self.inner)
+	}
+}
+
+impl<C: BlockCipher, W: Write> Write for CbcEncryptWriter<C, W> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		let bsize = self.cipher.block_size();
+		let mut data = Vec::with_capacity(self.partial.len() + buf.len());
+		data.extend_from_slice(&self.partial);
+		data.extend_from_slice(buf);
+
+		// keep the incomplete tail for the next write
+		let full = data.len() - data.len() % bsize;
+		self.partial = data.split_off(full);
+
+		self.encrypt_blocks(&mut data);
+		self.inner.write_all(&data)?;
+		Ok(buf.len())
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		self.inner.flush()
+	}
+}
+
+// CBC decryption that strips and checks the PKCS#7 padding at the end,
+// the last block is held back until the inner reader hits EOF
+pub struct CbcDecryptReader<C: BlockCipher, R: Read> {
+	inner: R,
+	cipher: C,
+	prev: Vec<u8>,
+	// ciphertext read, but not decrypted yet
+	pending: Vec<u8>,
+	// decrypted data not returned yet
+	out: Vec<u8>,
+	out_pos: usize,
+	eof: bool,
+	// bad ciphertext, reported again by every read
+	error: Option<String>,
+}
+
+impl<C: BlockCipher, R: Read> CbcDecryptReader<C, R> {
+	pub fn new(inner: R, cipher: C, iv: &[u8]) -> Result<CbcDecryptReader<C, R>, CryptoError> {
+		if iv.len() != cipher.block_size() {
+			return Err(CryptoError::IvLength(iv.len()));
+		}
+		Ok(CbcDecryptReader {
+			inner,
+			cipher,
+			prev: iv.to_vec(),
+			pending: Vec::new(),
+			out: Vec::new(),
+			out_pos: 0,
+			eof: false,
+			error: None,
+		})
+	}
+
+	fn decrypt_blocks(&mut self, mut data: Vec<u8>) -> Vec<u8> {
+		let bsize = self.cipher.block_size();
+		for block in data.chunks_mut(bsize) {
+			let enc = block.to_vec();
+			self.cipher.decrypt_block(block);
+			xor_slice(block, &self.prev);
+			self.prev = enc;
+		}
+		data
+	}
+
+	// decrypt the next batch of blocks into self.out
+	fn fill(&mut self) -> io::Result<()> {
+		let bsize = self.cipher.block_size();
+		let mut buf = vec![0u8; 64 * bsize];
+
+		while !self.eof && self.pending.len() <= bsize {
+			let count = self.inner.read(&mut buf)?;
+			if count == 0 {
+				self.eof = true;
+			}
+			self.pending.extend_from_slice(&buf[..count]);
+		}
+
+		if !self.eof {
+			// everything but the last (possibly padding) block
+			let ready = (self.pending.len() - 1) / bsize * bsize;
+			let rest = self.pending.split_off(ready);
+			let blocks = mem::replace(&mut self.pending, rest);
+			self.out = self.decrypt_blocks(blocks);
+		} else {
+			if self.pending.is_empty() || !self.pending.len().is_multiple_of(bsize) {
+				return Err(io::Error::new(io::ErrorKind::InvalidData, "truncated CBC ciphertext"));
+			}
+			let blocks = mem::take(&mut self.pending);
+			let mut plain = self.decrypt_blocks(blocks);
+			let len = Pkcs7.unpadded_len(&plain, bsize)
+				.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+			plain.truncate(len);
+			self.out = plain;
+		}
+		self.out_pos = 0;
+		Ok(())
+	}
+}
+
+impl<C: BlockCipher, R: Read> Read for CbcDecryptReader<C, R> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		while self.out_pos == self.out.len() {
+			if let Some(ref msg) = self.error {
+				return Err(io::Error::new(io::ErrorKind::InvalidData, msg.clone()));
+			}
+			// everything has been decrypted and returned
+			if self.eof && self.pending.is_empty() {
+				return Ok(0);
+			}
+			if let Err(e) = self.fill() {
+				// don't end the stream with Ok(0) after a bad padding,
+				// errors of the inner reader may be temporary
+				if e.kind() == io::ErrorKind::InvalidData {
+					self.error = Some(e.to_string());
+				}
+				return Err(e);
+			}
+		}
+
+		let count = buf.len().min(self.out.len() - self.out_pos);
+		buf[..count].copy_from_slice(&self.out[self.out_pos..self.out_pos + count]);
+		self.out_pos += count;
+		Ok(count)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::{Read, Write};
+
+	use utils::aes::Aes;
+	use utils::ctr::{CounterBlock, CounterLayout};
+	use utils::cryptodata::{CryptoData, CryptoError};
+	use utils::padding::Pkcs7;
+	use utils::stream::{CbcDecryptReader, CbcEncryptWriter, CtrReader, CtrWriter};
+
+	const LENGTHS: [usize; 5] = [0, 15, 16, 17, 33];
+	// 1 byte at a time, and a size that never lines up with the blocks
+	const CHUNKS: [usize; 2] = [1, 7];
+
+	fn write_chunked<W: Write>(w: &mut W, data: &[u8], chunk: usize) {
+		for piece in data.chunks(chunk) {
+			w.write_all(piece).unwrap();
+		}
+	}
+
+	fn read_chunked<R: Read>(r: &mut R, chunk: usize) -> Vec<u8> {
+		let mut result = Vec::new();
+		let mut buf = vec![0u8; chunk];
+		loop {
+			let count = r.read(&mut buf).unwrap();
+			if count == 0 {
+				return result;
+			}
+			result.extend_from_slice(&buf[..count]);
+		}
+	}
+
+	#[test]
+	fn ctr_round_trip() {
+		let key = CryptoData::random(16);
+		let nonce = CryptoData::random(8);
+		let start = || CounterBlock::from_nonce(&nonce, 0, CounterLayout::LittleEndian64, 16).unwrap();
+
+		for &len in LENGTHS.iter() {
+			let plain = CryptoData::random(len);
+			let expected = plain.CTR_encrypt(&key, &nonce, 0);
+			for &chunk in CHUNKS.iter() {
+				let mut w = CtrWriter::new(Vec::new(), Aes::new(&key).unwrap(), start());
+				write_chunked(&mut w, &plain, chunk);
+				let enc = w.into_inner();
+				assert_eq!(CryptoData::from_slice(&enc), expected);
+
+				let mut r = CtrReader::new(&enc[..], Aes::new(&key).unwrap(), start());
+				assert_eq!(CryptoData::from_owned(read_chunked(&mut r, chunk)), plain);
+			}
+		}
+	}
+
+	#[test]
+	fn cbc_round_trip() {
+		let key = CryptoData::random(16);
+		let iv = CryptoData::random(16);
+		let aes = Aes::new(&key).unwrap();
+
+		for &len in LENGTHS.iter() {
+			let plain = CryptoData::random(len);
+			let expected = plain.CBC_encrypt_padded(&aes, &iv, &Pkcs7).unwrap();
+			for &chunk in CHUNKS.iter() {
+				let mut w = CbcEncryptWriter::new(Vec::new(), Aes::new(&key).unwrap(), &iv).unwrap();
+				write_chunked(&mut w, &plain, chunk);
+				let enc = w.finish().unwrap();
+				assert_eq!(CryptoData::from_slice(&enc), expected);
+
+				let mut r = CbcDecryptReader::new(&enc[..], Aes::new(&key).unwrap(), &iv).unwrap();
+				assert_eq!(CryptoData::from_owned(read_chunked(&mut r, chunk)), plain);
+			}
+		}
+	}
+
+	#[test]
+	fn cbc_writer_needs_finish() {
+		let key = CryptoData::random(16);
+		let iv = CryptoData::random(16);
+		let plain = CryptoData::random(33);
+
+		let mut out = Vec::new();
+		{
+			let mut w = CbcEncryptWriter::new(&mut out, Aes::new(&key).unwrap(), &iv).unwrap();
+			w.write_all(&plain).unwrap();
+			// dropped without finish()
+		}
+		let expected = plain.CBC_encrypt(&key, &iv);
+		assert_eq!(out.len(), 32);
+		assert_eq!(&out[..], &expected[..32]);
+	}
+
+	#[test]
+	fn cbc_reader_keeps_failing() {
+		let key = CryptoData::random(16);
+		let iv = CryptoData::random(16);
+		let enc = CryptoData::random(33).CBC_encrypt(&key, &iv);
+		let mut corrupted = enc.vec().clone();
+		// turns the last pad byte 0x0f into 0xf0
+		corrupted[enc.len() - 17] ^= 0xff;
+
+		let mut r = CbcDecryptReader::new(&corrupted[..], Aes::new(&key).unwrap(), &iv).unwrap();
+		let mut buf = [0u8; 64];
+		// the first two blocks are fine
+		assert_eq!(r.read(&mut buf).unwrap(), 32);
+		for _ in 0..3 {
+			assert!(r.read(&mut buf).is_err());
+		}
+	}
+
+	#[test]
+	fn cbc_reader_rejects_truncated() {
+		let key = CryptoData::random(16);
+		let iv = CryptoData::random(16);
+		let enc = CryptoData::random(20).CBC_encrypt(&key, &iv);
+
+		for &len in [0, 17].iter() {
+			let mut r = CbcDecryptReader::new(&enc[..len], Aes::new(&key).unwrap(), &iv).unwrap();
+			let mut out = Vec::new();
+			assert!(r.read_to_end(&mut out).is_err());
+			assert!(r.read(&mut [0u8; 64]).is_err());
+		}
+	}
+
+	#[test]
+	fn cbc_bad_iv() {
+		let aes = || Aes::new(&[0u8; 16]).unwrap();
+		assert!(matches!(CbcEncryptWriter::new(Vec::new(), aes(), &[0u8; 8]), Err(CryptoError::IvLength(8))));
+		assert!(matches!(CbcDecryptReader::new(&[][..], aes(), &[0u8; 17]), Err(CryptoError::IvLength(17))));
+	}
+}
@@ -42,8 +42,7 @@ fn eng_char_freq(c: char) -> f32 {
 }
 
 fn score_bytes(data: &CryptoData) -> f32 {
-	let it = data.vec().iter();
-	it.fold(0.0, |x, y| x + eng_char_freq((*y as char).to_ascii_uppercase()))
+	data.iter().fold(0.0, |x, y| x + eng_char_freq((*y as char).to_ascii_uppercase()))
 }
 
 pub fn guess_xor_byte(xored: &CryptoData) -> (CryptoData, CryptoData, f32) {
@@ -72,11 +71,11 @@ pub fn guess_xor_key(enc: &CryptoData, keysize: usize) -> CryptoData {
 	for position in 0..keysize {
 		let mut bytes = Vec::new();
 		for block in 0..(enc.len() / keysize) {
-			bytes.push(enc.vec()[block * keysize + position]);
+			bytes.push(enc[block * keysize + position]);
 		}
-		let (ch, _, _) = guess_xor_byte(&CryptoData::from_vec(&bytes));
+		let (ch, _, _) = guess_xor_byte(&CryptoData::from_owned(bytes));
 		//println!("char: {}, score: {}", ch, best_score);
-		key = key.cat(&ch);
+		key.extend(&ch);
 	}
 	key
 }
@@ -102,7 +101,11 @@ pub fn wrap_and_encrypt_CTR(input: &str, key: &CryptoData, nonce: &CryptoData, c
 }
 
 fn and_find(decrypted: &CryptoData) -> bool {
-	let s = decrypted.to_text();
+	// the scrambled block is rarely valid UTF-8
+	let s = match decrypted.try_to_text() {
+		Ok(s) => s,
+		Err(_) => String::from_utf8_lossy(decrypted).into_owned(),
+	};
 	println!("decrypted: {}", s);
 	println!("decrypted: {}", decrypted.to_hex());
 	match s.find(";admin=true;") {
@@ -123,9 +126,9 @@ pub fn decrypt_and_find_CTR(input: &CryptoData, key: &CryptoData, nonce: &Crypto
 
 //TODO: more general solution
 pub fn flip_bits(input: &CryptoData, positions: &Vec<usize>) -> CryptoData {
-	let mut vec = input.vec().clone();
-	for idx in positions.iter() {
-		vec[*idx] = vec[*idx] ^ 1;
+	let mut out = input.vec().clone();
+	for &i in positions {
+		out[i] ^= 1;
 	}
-	CryptoData::from_vec(&vec)
+	CryptoData::from_owned(out)
 }
@@ -4,9 +4,12 @@ extern crate rustful_macros;
 
 extern crate rustful;
 extern crate http;
-extern crate "rust-crypto" as rust_crypto;
-extern crate serialize;
-extern crate collections;
+
+// dependencies of the shared utils
+extern crate openssl;
+extern crate rand;
+extern crate base64;
+extern crate hex;
 
 //Include `rustful_macros` during the plugin phase
 //to be able to use `router!` and `try_send!`.
@@ -14,12 +17,13 @@ use rustful::{Server, Request, Response};
 use http::method::Get;
 use http::status::Status;
 
+use utils::cryptodata::CryptoData;
+use utils::sha1::Sha1;
+
+#[path = "../utils/mod.rs"]
+mod utils;
+
 fn say_hello(request: Request, response: &mut Response) {
-	use self::rust_crypto::hmac::{Hmac};
-	use self::rust_crypto::mac::{Mac};
-	use self::rust_crypto::sha1::{Sha1};
-	use self::rust_crypto::digest::Digest;
-	use self::serialize::hex::{ToHex};
 	use std::io::Timer;
 	use std::time::Duration;
 
@@ -35,13 +39,7 @@ fn say_hello(request: Request, response: &mut Response) {
 
 	let key = "abc";
 
-	let sha = Sha1::new();
-	let mut hmac = Hmac::new(sha, key.as_bytes());
-
-	hmac.input(file.as_bytes());
-	let macres = hmac.result();
-	let vec: Vec<u8> = macres.code().iter().map(|x| *x).collect();
-	let comp_sig = vec.as_slice().to_hex();
+	let comp_sig = CryptoData::from(file).HMAC::<Sha1>(&CryptoData::from(key)).to_hex();
 	//println!("get: {}\nmy : {}", sig, comp_sig);
 
 	let mut timer = Timer::new().unwrap();